@@ -0,0 +1,46 @@
+//! Export and import tags without running the bot, e.g. to keep them reviewed in git.
+//! Reads the database from `DATABASE_URL`, just like the bot itself.
+//!
+//! Importing only shows what would change, unless `--apply` is given.
+
+use anyhow::{bail, Context};
+use robbb_db::Db;
+use robbb_util::tag_archive::{self, TagArchiveDiff};
+
+const USAGE: &str =
+    "Usage: robbb-tags export <archive.zip> | robbb-tags import <archive.zip> [--apply]";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|x| x.as_str()).collect();
+
+    let db = Db::new().await.context("Failed to open database")?;
+    db.run_migrations().await?;
+
+    match args.as_slice() {
+        ["export", path] => {
+            let tags = tag_archive::load_tag_files(&db).await?;
+            let archive = tag_archive::write_archive(&tags)?;
+            std::fs::write(path, archive).with_context(|| format!("Failed to write {path}"))?;
+            println!("Exported {} tags to {path}", tags.len());
+        }
+        ["import", path, rest @ ..] if rest.is_empty() || rest == ["--apply"] => {
+            let bytes = std::fs::read(path).with_context(|| format!("Failed to read {path}"))?;
+            let incoming = tag_archive::read_archive(&bytes)?;
+            let current = tag_archive::load_tag_files(&db).await?;
+            let diff = TagArchiveDiff::new(&current, &incoming);
+            print!("{diff}");
+            if diff.is_empty() {
+                println!("Nothing to import");
+            } else if rest.is_empty() {
+                println!("Run again with --apply to import these changes");
+            } else {
+                tag_archive::apply_import(&db, &incoming, &diff).await?;
+                println!("Imported tags");
+            }
+        }
+        _ => bail!(USAGE),
+    }
+    Ok(())
+}
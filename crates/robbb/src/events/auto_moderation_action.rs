@@ -40,5 +40,13 @@ pub async fn execution(ctx: client::Context, execution: ActionExecution) -> anyh
         robbb_db::mod_action::ModActionKind::BlocklistViolation,
     )
     .await?;
+
+    // Automod keywords are usually mirrored from our blocklist, but may also be managed in discord directly.
+    // In that case, the keyword itself is what we keep stats for.
+    let pattern = match db.find_blocklist_pattern(&matched_content).await? {
+        Some(pattern) => pattern,
+        None => matched_keyword,
+    };
+    db.record_blocklist_hit(&pattern, execution.user_id).await?;
     Ok(())
 }
@@ -0,0 +1,132 @@
+use itertools::Itertools;
+use serenity::{
+    all::ComponentInteraction,
+    builder::{CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage},
+};
+
+use robbb_commands::{
+    checks::{self, PermissionLevel},
+    commands,
+};
+
+use super::*;
+
+/// Handle component interactions on messages the bot posted on its own, outside of any running command,
+/// such as the buttons on automatic modlog entries and tag proposals, tag suggestions, or the verification button.
+/// Returns true if the interaction was handled here.
+#[tracing::instrument(skip_all, fields(interaction.custom_id = %interaction.data.custom_id))]
+pub async fn handle_component_interaction(
+    ctx: &client::Context,
+    interaction: &ComponentInteraction,
+) -> Result<bool> {
+    let custom_id = interaction.data.custom_id.as_str();
+    if custom_id == commands::verification::VERIFICATION_BUTTON {
+        handle_verification::handle_verification_button(ctx, interaction).await?;
+        return Ok(true);
+    }
+
+    if handle_tag_suggestions::handle_tag_suggestion_button(ctx, interaction).await? {
+        return Ok(true);
+    }
+
+    let Some(button) = ModlogButton::parse(custom_id)? else { return Ok(false) };
+
+    if checks::get_permission_level(ctx, &interaction.user).await? != PermissionLevel::Mod {
+        respond_ephemeral(ctx, interaction, "Only moderators can do this").await?;
+        return Ok(true);
+    }
+
+    let outcome = match button {
+        ModlogButton::PhishingBan(user_id) => {
+            handle_phishing::handle_phishing_ban_button(ctx, interaction, user_id).await.map(Some)
+        }
+        ModlogButton::PhishingFalsePositive(user_id) => {
+            handle_phishing::handle_phishing_false_positive_button(ctx, interaction, user_id)
+                .await
+                .map(Some)
+        }
+        ModlogButton::TagProposalApprove { id, official } => {
+            handle_tag_proposal::handle_tag_proposal_approve_button(ctx, interaction, id, official)
+                .await
+        }
+        ModlogButton::TagProposalEdit(id) => {
+            handle_tag_proposal::handle_tag_proposal_edit_button(ctx, interaction, id).await
+        }
+        ModlogButton::TagProposalReject(id) => {
+            handle_tag_proposal::handle_tag_proposal_reject_button(ctx, interaction, id).await
+        }
+    };
+
+    match outcome {
+        // The handler already responded to the interaction on its own
+        Ok(None) => {}
+        Ok(Some(resolution)) => {
+            let embeds = interaction
+                .message
+                .embeds
+                .iter()
+                .cloned()
+                .map(|embed| CreateEmbed::from(embed).field("Resolution", &resolution, false))
+                .collect_vec();
+            let response =
+                CreateInteractionResponseMessage::default().embeds(embeds).components(vec![]);
+            interaction
+                .create_response(&ctx, CreateInteractionResponse::UpdateMessage(response))
+                .await?;
+        }
+        Err(err) => {
+            respond_ephemeral(ctx, interaction, "Something went wrong, please do it manually")
+                .await?;
+            return Err(err);
+        }
+    }
+    Ok(true)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ModlogButton {
+    PhishingBan(UserId),
+    PhishingFalsePositive(UserId),
+    TagProposalApprove { id: i64, official: bool },
+    TagProposalEdit(i64),
+    TagProposalReject(i64),
+}
+
+impl ModlogButton {
+    /// Parse the custom_id of a button, returning None if it isn't one of the modlog buttons.
+    fn parse(custom_id: &str) -> Result<Option<Self>> {
+        use commands::tag::*;
+        let parse_user = |x: &str| x.parse::<UserId>().context("Invalid user id in custom_id");
+        let parse_id = |x: &str| x.parse::<i64>().context("Invalid id in custom_id");
+
+        Ok(Some(
+            if let Some(user_id) = custom_id.strip_prefix(handle_phishing::PHISHING_BAN_BUTTON) {
+                ModlogButton::PhishingBan(parse_user(user_id)?)
+            } else if let Some(user_id) =
+                custom_id.strip_prefix(handle_phishing::PHISHING_FALSE_POSITIVE_BUTTON)
+            {
+                ModlogButton::PhishingFalsePositive(parse_user(user_id)?)
+            } else if let Some(id) = custom_id.strip_prefix(TAG_PROPOSAL_APPROVE_BUTTON) {
+                ModlogButton::TagProposalApprove { id: parse_id(id)?, official: false }
+            } else if let Some(id) = custom_id.strip_prefix(TAG_PROPOSAL_APPROVE_OFFICIAL_BUTTON) {
+                ModlogButton::TagProposalApprove { id: parse_id(id)?, official: true }
+            } else if let Some(id) = custom_id.strip_prefix(TAG_PROPOSAL_EDIT_BUTTON) {
+                ModlogButton::TagProposalEdit(parse_id(id)?)
+            } else if let Some(id) = custom_id.strip_prefix(TAG_PROPOSAL_REJECT_BUTTON) {
+                ModlogButton::TagProposalReject(parse_id(id)?)
+            } else {
+                return Ok(None);
+            },
+        ))
+    }
+}
+
+pub async fn respond_ephemeral(
+    ctx: &client::Context,
+    interaction: &ComponentInteraction,
+    content: &str,
+) -> Result<()> {
+    let response = CreateInteractionResponseMessage::default().content(content).ephemeral(true);
+    interaction.create_response(&ctx, CreateInteractionResponse::Message(response)).await?;
+    Ok(())
+}
@@ -68,6 +68,8 @@ pub async fn guild_member_addition(ctx: client::Context, mut new_member: Member)
 
     log_error!(handle_htm_evasion(&ctx, &mut new_member).await);
     log_error!(handle_mute_evasion(&ctx, &new_member).await);
+    log_error!(handle_blocklist::handle_blocklist_in_member(&ctx, &new_member).await);
+    log_error!(handle_verification::start_member_verification(&ctx, &new_member).await);
 
     let account_created_at = new_member.user.created_at();
     config
@@ -44,5 +44,8 @@ pub async fn guild_member_removal(
         })
         .await?;
     db.rm_highlights_of(user.id).await?;
+    db.rm_highlight_ignores_of(user.id).await?;
+    db.rm_highlight_settings_of(user.id).await?;
+    db.remove_verification(user.id).await?;
     Ok(())
 }
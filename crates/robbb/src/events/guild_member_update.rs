@@ -13,7 +13,10 @@ pub async fn guild_member_update(
 ) -> Result<()> {
     let (config, db) = ctx.get_config_and_db().await;
     if let Some(new) = new {
-        dehoist_member(ctx.clone(), new.clone()).await?;
+        let nickname_reset = check_member_name_blocklist(&ctx, &new).await;
+        if !nickname_reset {
+            dehoist_member(ctx.clone(), new.clone()).await?;
+        }
     }
 
     if event.roles.iter().any(|x| *x == config.role_htm) {
@@ -23,6 +26,15 @@ pub async fn guild_member_update(
     Ok(())
 }
 
+/// Check a members names against the blocklist, logging any errors.
+/// Returns true if their nickname was reset, in which case they shouldn't be dehoisted anymore.
+pub async fn check_member_name_blocklist(ctx: &client::Context, member: &Member) -> bool {
+    handle_blocklist::handle_blocklist_in_member(ctx, member).await.unwrap_or_else(|err| {
+        tracing::error!(error.message = %err, "Error while checking a members name against the blocklist");
+        false
+    })
+}
+
 pub async fn dehoist_member(ctx: client::Context, mut member: Member) -> Result<()> {
     let display_name = member.display_name().to_string();
     if !display_name.starts_with(HOISTING_CHAR) {
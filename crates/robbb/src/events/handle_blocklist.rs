@@ -1,25 +1,31 @@
 use chrono::Utc;
 use robbb_commands::{
     checks::{self, PermissionLevel},
-    commands::blocklist::SHOULD_NEVER_TRIGGER_BLOCKLIST,
+    commands::blocklist::{INVISIBLE_CHARS, SHOULD_NEVER_TRIGGER_BLOCKLIST},
+};
+use robbb_db::{
+    mod_action::{ModActionKind, ModActionType},
+    Db,
 };
-use robbb_db::mod_action::ModActionKind;
 use robbb_util::util::{generate_message_link, time_to_discord_snowflake};
 use serenity::{
     all::{CommandInteraction, ResolvedValue},
-    builder::{CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage},
+    builder::{
+        CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, EditMember,
+    },
 };
 use tracing_futures::Instrument;
 
 use super::*;
 
-const INVISIBLE_CHARS: &[char] = &['\u{200b}', '\u{200c}', '\u{200d}', '\u{200e}', '\u{200f}'];
+/// Nickname given to members whose nickname or global name contains a blocked word.
+const BLOCKED_NAME_PLACEHOLDER: &str = "Moderated Nickname";
 
 /// If the message contains a blocked word, delete the message, notify the user and #bot-auto-mod.
 /// Returns true if the message had a blocked word, in which case handling the message_create event should be stopped early.
 #[tracing::instrument(skip_all, fields(blocklist.blocked_word, msg.author = %msg.author.tag(), %msg.id))]
 pub async fn handle_blocklist(ctx: &client::Context, msg: &Message) -> Result<bool> {
-    let (config, db) = ctx.get_config_and_db().await;
+    let db = ctx.get_db().await;
 
     // remove invisible characters
     let normalized_msg = msg.content.replace(INVISIBLE_CHARS, "");
@@ -39,48 +45,21 @@ pub async fn handle_blocklist(ctx: &client::Context, msg: &Message) -> Result<bo
         tracing::info!(blocklist.word = %word, "Found blocked word '{}'", word);
         tracing::Span::current().record("blocklist.blocked_word", word);
 
-        let dm_embed = CreateEmbed::default()
-            .description(&msg.content)
-            .title(
-                format!("Your message has been deleted for containing a blocked word: `{word}`",),
-            )
-            .into_create_message();
-        let dm_future = async {
-            let _ = msg.author.dm(&ctx, dm_embed).await;
+        let reason = AutodeleteReason {
+            dm_reason: format!("containing a blocked word: `{word}`"),
+            cause: word.to_string(),
+            note: format!("Message deleted because of word `{word}`"),
         };
 
-        let bot_log_future = config.log_automod_action(&ctx, |e| {
-            e.author_user(&msg.author)
-                .title("Message Autodelete")
-                .field("Deleted because of", word, false)
-                .description(format!("{}\n{}", msg.content, msg.to_context_link()))
-        });
-
-        let note_future = async {
-            let bot_id = ctx.cache.current_user().id;
-            let note_content = format!("Message deleted because of word `{word}`");
-            let _ = db
-                .add_mod_action(
-                    bot_id,
-                    msg.author.id,
-                    note_content,
-                    Utc::now(),
-                    msg.link(),
-                    ModActionKind::BlocklistViolation,
-                )
-                .await;
+        let stats_future = async {
+            log_error!(record_blocklist_hit(&db, word, &normalized_msg, msg.author.id).await);
         };
 
-        // well, msg.delete does not work for some reason,...
-        let delete_future = msg.channel_id.delete_message(ctx, msg.id);
-
         tokio::join!(
-            dm_future.instrument(tracing::debug_span!("blocklist-dm")),
-            bot_log_future.instrument(tracing::debug_span!("blocklist-automod-entry")),
-            note_future.instrument(tracing::debug_span!("blocklist-note")),
-            delete_future.instrument(tracing::debug_span!("blocklist-delete"))
+            autodelete_message(ctx, msg, reason),
+            stats_future.instrument(tracing::debug_span!("blocklist-stats")),
         )
-        .3?;
+        .0?;
 
         Ok(true)
     } else {
@@ -104,7 +83,9 @@ pub async fn handle_blocklist_in_command_interaction(
     for value in &values.values {
         let normalized = value.replace(INVISIBLE_CHARS, "");
         if let Some(word) = blocklist_regex.find(&normalized) {
-            handle_blocked_word_in_interaction(ctx, interaction, word.as_str(), values).await;
+            let word = word.as_str();
+            log_error!(record_blocklist_hit(&db, word, &normalized, values.user.id).await);
+            handle_blocked_word_in_interaction(ctx, interaction, word, values).await;
             return Ok(true);
         }
     }
@@ -165,6 +146,210 @@ async fn handle_blocked_word_in_interaction(
     );
 }
 
+/// Why a message was deleted automatically.
+#[derive(Debug)]
+pub struct AutodeleteReason {
+    /// Completes "Your message has been deleted for ..." in the DM sent to the author.
+    pub dm_reason: String,
+    /// Shown in #bot-auto-mod as the cause of the deletion.
+    pub cause: String,
+    /// Content of the note added to the author.
+    pub note: String,
+}
+
+/// Delete a message, notify the author and #bot-auto-mod, and add a note to the author.
+#[tracing::instrument(skip_all, fields(autodelete.cause = %reason.cause, msg.author = %msg.author.tag(), %msg.id))]
+pub async fn autodelete_message(
+    ctx: &client::Context,
+    msg: &Message,
+    reason: AutodeleteReason,
+) -> Result<()> {
+    let (config, db) = ctx.get_config_and_db().await;
+
+    let dm_embed = CreateEmbed::default()
+        .description(&msg.content)
+        .title(format!("Your message has been deleted for {}", reason.dm_reason))
+        .into_create_message();
+    let dm_future = async {
+        ctx.get_dm_queue().await.send(msg.author.id, dm_embed);
+    };
+
+    let bot_log_future = config.log_automod_action(&ctx, |e| {
+        e.author_user(&msg.author)
+            .title("Message Autodelete")
+            .field("Deleted because of", &reason.cause, false)
+            .description(format!("{}\n{}", msg.content, msg.to_context_link()))
+    });
+
+    let note_future = async {
+        let bot_id = ctx.cache.current_user().id;
+        let _ = db
+            .add_mod_action(
+                bot_id,
+                msg.author.id,
+                reason.note.clone(),
+                Utc::now(),
+                msg.link(),
+                ModActionKind::BlocklistViolation,
+            )
+            .await;
+    };
+
+    // well, msg.delete does not work for some reason,...
+    let delete_future = msg.channel_id.delete_message(ctx, msg.id);
+
+    tokio::join!(
+        dm_future.instrument(tracing::debug_span!("blocklist-dm")),
+        bot_log_future.instrument(tracing::debug_span!("blocklist-automod-entry")),
+        note_future.instrument(tracing::debug_span!("blocklist-note")),
+        delete_future.instrument(tracing::debug_span!("blocklist-delete"))
+    )
+    .3?;
+    Ok(())
+}
+
+/// Check the nickname, global name and username of a member against the blocklist.
+/// Nicknames and global names get covered up by resetting the nickname to a neutral placeholder.
+/// Usernames can't be changed by us, so mods get alerted about those instead.
+/// Returns true if the nickname of the member was changed.
+#[tracing::instrument(skip_all, fields(blocklist.blocked_word, member.tag = %member.user.tag()))]
+pub async fn handle_blocklist_in_member(ctx: &client::Context, member: &Member) -> Result<bool> {
+    let (config, db) = ctx.get_config_and_db().await;
+    if member.roles.contains(&config.role_mod) {
+        return Ok(false);
+    }
+
+    let blocklist_regex = db.get_combined_blocklist_regex().await?;
+    if SHOULD_NEVER_TRIGGER_BLOCKLIST.iter().any(|x| blocklist_regex.is_match(x)) {
+        tracing::error!("Blocklist regex matches one of the sanity check patterns. Make sure none of the blocklist entries match the empty string.");
+        return Ok(false);
+    }
+
+    let find_blocked_word = |name: &str| {
+        let normalized = name.replace(INVISIBLE_CHARS, "");
+        let word = blocklist_regex.find(&normalized)?.as_str().to_string();
+        Some((word, normalized))
+    };
+
+    let mut nickname_reset = false;
+    // Once the nickname is the placeholder, the global name isn't visible in the member list anymore.
+    if member.nick.as_deref() != Some(BLOCKED_NAME_PLACEHOLDER) {
+        let names = [
+            ("Nickname", member.nick.as_deref()),
+            ("Global name", member.user.global_name.as_deref()),
+        ];
+        for (kind, name) in names {
+            let Some(name) = name else { continue };
+            let Some((word, normalized)) = find_blocked_word(name) else { continue };
+
+            tracing::info!(blocklist.word = %word, "Found blocked word '{word}' in {kind} '{name}'");
+            tracing::Span::current().record("blocklist.blocked_word", word.as_str());
+
+            config
+                .guild
+                .edit_member(
+                    &ctx,
+                    member.user.id,
+                    EditMember::default().nickname(BLOCKED_NAME_PLACEHOLDER),
+                )
+                .await
+                .with_context(|| format!("Failed to reset nickname of {}", member.user.tag()))?;
+
+            let note = format!("{kind} `{name}` reset because of word `{word}`");
+            report_blocked_name(ctx, &db, member, kind, name, &word, note).await;
+            log_error!(record_blocklist_hit(&db, &word, &normalized, member.user.id).await);
+            nickname_reset = true;
+            break;
+        }
+    }
+
+    if let Some((word, normalized)) = find_blocked_word(&member.user.name) {
+        let note = format!("Username `{}` contains blocked word `{word}`", member.user.name);
+
+        // The ready sweep sees the same usernames on every restart, so only alert once per username.
+        let already_reported = db
+            .get_mod_actions(member.user.id, Some(ModActionType::BlocklistViolation))
+            .await?
+            .iter()
+            .any(|x| x.reason == note);
+        if !already_reported {
+            tracing::info!(blocklist.word = %word, "Found blocked word '{word}' in username '{}'", member.user.name);
+            tracing::Span::current().record("blocklist.blocked_word", word.as_str());
+            report_blocked_name(ctx, &db, member, "Username", &member.user.name, &word, note).await;
+            log_error!(record_blocklist_hit(&db, &word, &normalized, member.user.id).await);
+        }
+    }
+
+    Ok(nickname_reset)
+}
+
+/// Log a blocked word in a members name to #bot-auto-mod and add a note about it.
+async fn report_blocked_name(
+    ctx: &client::Context,
+    db: &Db,
+    member: &Member,
+    kind: &str,
+    name: &str,
+    word: &str,
+    note: String,
+) {
+    let config = ctx.get_config().await;
+    let action = if kind == "Username" {
+        "Usernames can't be changed by the bot, please take a look."
+    } else {
+        "Reset their nickname."
+    };
+    let bot_log_future = ctx.log_automod_action(|e| {
+        e.author_user(&member.user)
+            .title(format!("Blocked word in {}", kind.to_lowercase()))
+            .description(format!("{} {}\n{action}", member.mention(), name))
+            .field("Blocked because of", word, false)
+    });
+
+    let note_future = async {
+        let bot_id = ctx.cache.current_user().id;
+        let context_link = generate_message_link(
+            Some(config.guild),
+            config.channel_auto_mod,
+            time_to_discord_snowflake(Utc::now()),
+        );
+        log_error!(
+            db.add_mod_action(
+                bot_id,
+                member.user.id,
+                note,
+                Utc::now(),
+                context_link,
+                ModActionKind::BlocklistViolation,
+            )
+            .await
+        );
+    };
+
+    tokio::join!(
+        bot_log_future.instrument(tracing::debug_span!("blocklist-automod-entry")),
+        note_future.instrument(tracing::debug_span!("blocklist-note")),
+    );
+}
+
+/// Record a hit in the blocklist statistics.
+/// Figures out which pattern was responsible by first checking the matched word, and then the full text it was found in.
+pub async fn record_blocklist_hit(
+    db: &Db,
+    matched: &str,
+    full_text: &str,
+    offender: UserId,
+) -> Result<()> {
+    let pattern = match db.find_blocklist_pattern(matched).await? {
+        Some(pattern) => Some(pattern),
+        None => db.find_blocklist_pattern(full_text).await?,
+    };
+    if let Some(pattern) = pattern {
+        db.record_blocklist_hit(&pattern, offender).await?;
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 struct InteractionValues<'a> {
     values: Vec<&'a str>,
@@ -174,7 +359,7 @@ struct InteractionValues<'a> {
     title: &'a str,
 }
 
-fn collect_interaction_values(interaction: &CommandInteraction) -> InteractionValues {
+fn collect_interaction_values(interaction: &CommandInteraction) -> InteractionValues<'_> {
     fn values_from_resolved_value<'a>(value: &ResolvedValue<'a>) -> Vec<&'a str> {
         match value {
             ResolvedValue::String(s) => vec![s],
@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+
+use chrono::Utc;
+use itertools::Itertools;
+use robbb_db::highlights::HighlightMatch;
+use serenity::all::GuildChannel;
+use serenity::builder::CreateEmbed;
+use tracing_futures::Instrument;
+
+use crate::highlighting::{HighlightState, PendingHighlight};
+
+use super::*;
+
+/// Number of messages before the highlighted one that are shown in the notification.
+const HIGHLIGHT_CONTEXT_MESSAGES: usize = 3;
+
+/// Cooldowns are forgotten after this long, so it is also the maximum cooldown.
+pub const MAX_HIGHLIGHT_COOLDOWN: std::time::Duration = std::time::Duration::from_secs(60 * 60 * 2);
+
+#[tracing::instrument(skip_all, fields(highlights.notified_user_cnt))]
+pub async fn handle_highlighting(ctx: &client::Context, msg: &Message) -> Result<usize> {
+    let (config, db) = ctx.get_config_and_db().await;
+    let state = get_highlight_state(ctx).await?;
+    let dm_queue = ctx.get_dm_queue().await;
+    state.record_activity(msg.author.id, msg.channel_id);
+
+    let highlights_data = db.get_highlights().await?;
+
+    let highlight_matches = tokio::task::spawn_blocking({
+        let msg_content = msg.content.to_string();
+        move || highlights_data.get_triggers_for_message(&msg_content)
+    })
+    .instrument(tracing::debug_span!("highlights-trigger-check"))
+    .await
+    .context("Failed to get highlight triggers for a message")?;
+
+    if highlight_matches.is_empty() {
+        tracing::Span::current().record("highlights.notified_user_cnt", 0i32);
+        return Ok(0);
+    }
+    // don't highlight in threads or mod internal channels
+    // We do this after checking for highlights as checking for highlights is a lot
+    // cheaper than potentially sending discord API requests for
+    // a lot of messages, specifically in threads
+    let channel = msg
+        .channel(&ctx)
+        .await
+        .context("Couldn't get channel")?
+        .guild()
+        .context("Couldn't get a guild-channel from the channel")?;
+    if channel.thread_metadata.is_some()
+        || config.category_mod_private == channel.parent_id.context("Couldn't get category_id")?
+        || config.category_modmail == channel.parent_id.context("Couldn't get category_id")?
+    {
+        tracing::Span::current().record("highlights.notified_user_cnt", 0i32);
+        return Ok(0);
+    }
+
+    let ignores = db.get_highlight_ignores().await?;
+    let all_settings = db.get_highlight_settings().await?;
+    let mut handled_users = HashSet::new();
+    // whether each recipient can read the channel, so it's computed at most once per message
+    let mut visibility = HashMap::<UserId, bool>::new();
+    let previous_messages = get_previous_messages(ctx, msg);
+    for HighlightMatch { trigger, matched, range, users } in highlight_matches {
+        let word = &trigger.word;
+        let conversation = format_conversation(&previous_messages, msg, range);
+        let embed = CreateEmbed::default()
+            .title("Highlight notification")
+            .description(indoc::formatdoc!(
+                "`{matched}` has been mentioned in {}
+                {conversation}
+                [link to message]({})
+
+                Don't care about this anymore?
+                Run `!highlights remove {word}` in #bot to stop getting these notifications.",
+                msg.channel_id.mention(),
+                msg.link(),
+            ))
+            .author_user(&msg.author)
+            .timestamp(msg.timestamp)
+            .footer_str(format!("#{}", channel.name));
+
+        tracing::debug!(
+            highlights.word = %word,
+            highlights.users = ?users,
+            highlights.users_count = %users.len(),
+            "Notifying {} users about a mention of the word '{word}'", users.len()
+        );
+
+        let create_message = embed.into_create_message();
+        for user_id in users {
+            if user_id == msg.author.id
+                // check if the user has already been notified of another word in this message
+                || handled_users.contains(&user_id)
+                // check if the user ignores this channel, its category or the author
+                || ignores.is_ignored(user_id, msg.channel_id, channel.parent_id, msg.author.id)
+            {
+                continue;
+            }
+            // check if the user can read that channel
+            let can_read = match visibility.get(&user_id) {
+                Some(can_read) => *can_read,
+                None => {
+                    let can_read = can_read_channel(ctx, &channel, user_id).await;
+                    visibility.insert(user_id, can_read);
+                    can_read
+                }
+            };
+            if !can_read {
+                continue;
+            }
+            handled_users.insert(user_id);
+
+            let settings = all_settings.get(&user_id).cloned().unwrap_or_default();
+            let cooldown = std::time::Duration::from_secs(settings.cooldown_minutes as u64 * 60);
+            if state.check_cooldown(user_id, msg.channel_id, cooldown)
+                || (settings.suppress_when_active
+                    && state.was_active_recently(user_id, msg.channel_id))
+            {
+                continue;
+            }
+            if settings.digest_minutes.is_some() || settings.is_quiet_at(Utc::now()) {
+                state.queue_digest(
+                    user_id,
+                    PendingHighlight {
+                        matched: matched.clone(),
+                        channel_id: msg.channel_id,
+                        author: msg.author.tag(),
+                        link: msg.link(),
+                        timestamp: msg.timestamp,
+                    },
+                );
+                continue;
+            }
+
+            dm_queue.send(user_id, create_message.clone());
+        }
+    }
+
+    tracing::Span::current().record("highlights.notified_user_cnt", handled_users.len());
+    Ok(handled_users.len())
+}
+
+/// Check if the user can read the channel, based on their roles and the channels permission overwrites.
+/// Members are taken from the cache, only falling back to fetching them if they aren't cached.
+async fn can_read_channel(ctx: &client::Context, channel: &GuildChannel, user_id: UserId) -> bool {
+    let cached_permissions = ctx.cache.guild(channel.guild_id).map(|guild| {
+        guild.members.get(&user_id).map(|member| guild.user_permissions_in(channel, member))
+    });
+    let permissions = match cached_permissions {
+        None => return false,
+        Some(Some(permissions)) => permissions,
+        Some(None) => {
+            let Ok(member) = channel.guild_id.member(&ctx, user_id).await else {
+                // most likely the user left the server
+                return false;
+            };
+            let Some(guild) = ctx.cache.guild(channel.guild_id) else { return false };
+            guild.user_permissions_in(channel, &member)
+        }
+    };
+    permissions.view_channel() && permissions.read_message_history()
+}
+
+/// Get the messages sent in the channel right before the given message, oldest first, as far as they are cached.
+fn get_previous_messages(ctx: &client::Context, msg: &Message) -> Vec<Message> {
+    let Some(channel_messages) = ctx.cache.channel_messages(msg.channel_id) else {
+        return Vec::new();
+    };
+    let mut previous_messages =
+        channel_messages.values().filter(|x| x.id < msg.id).cloned().collect_vec();
+    previous_messages.sort_by_key(|x| x.id);
+    let skip_cnt = previous_messages.len().saturating_sub(HIGHLIGHT_CONTEXT_MESSAGES);
+    previous_messages.split_off(skip_cnt)
+}
+
+/// Format the previous messages followed by the highlighted message, with the matched text in bold.
+fn format_conversation(
+    previous_messages: &[Message],
+    msg: &Message,
+    range: std::ops::Range<usize>,
+) -> String {
+    let format_line =
+        |author: &str, content: &str| format!("> **{}**: {}", author, content.replace('\n', " "));
+    let mut lines = previous_messages
+        .iter()
+        .filter(|x| !x.content.is_empty())
+        .map(|x| format_line(&x.author.name, &util::ellipsis_text(&x.content, 200)))
+        .collect_vec();
+
+    let content = &msg.content;
+    let before = tail_chars(&content[..range.start], 300);
+    let after = util::ellipsis_text(&content[range.end..], 300);
+    let emphasized = format!("{before}**{}**{after}", &content[range]);
+    lines.push(format_line(&msg.author.name, &emphasized));
+    lines.join("\n")
+}
+
+/// Get the last `max_len` characters of a string, prefixed with `...` if anything was cut off.
+fn tail_chars(text: &str, max_len: usize) -> String {
+    let char_cnt = text.chars().count();
+    if char_cnt <= max_len {
+        text.to_string()
+    } else {
+        format!("...{}", text.chars().skip(char_cnt - max_len).collect::<String>())
+    }
+}
+
+/// Send out the digests of all users whose digest interval passed and who aren't in their quiet hours.
+/// Users without digest mode that got highlighted during their quiet hours get their digest once those end.
+#[tracing::instrument(skip_all)]
+pub async fn send_highlight_digests(ctx: &client::Context) -> Result<()> {
+    let db = ctx.get_db().await;
+    let state = get_highlight_state(ctx).await?;
+    let dm_queue = ctx.get_dm_queue().await;
+    let all_settings = db.get_highlight_settings().await?;
+    state.cleanup(MAX_HIGHLIGHT_COOLDOWN);
+
+    let now = Utc::now();
+    let due_digests = state.take_due_digests(|user_id| {
+        let settings = all_settings.get(&user_id).cloned().unwrap_or_default();
+        if settings.is_quiet_at(now) {
+            None
+        } else {
+            let minutes = settings.digest_minutes.unwrap_or_default();
+            Some(std::time::Duration::from_secs(minutes as u64 * 60))
+        }
+    });
+
+    for digest in due_digests {
+        tracing::debug!(
+            user.id = %digest.user_id,
+            highlights.digest_len = digest.highlights.len(),
+            "Sending highlight digest"
+        );
+        let mut description = digest
+            .highlights
+            .iter()
+            .map(|x| {
+                format!(
+                    "`{}` by {} in {} <t:{}:R> - [link]({})",
+                    x.matched,
+                    x.author,
+                    x.channel_id.mention(),
+                    x.timestamp.unix_timestamp(),
+                    x.link
+                )
+            })
+            .join("\n");
+        if digest.omitted_cnt > 0 {
+            description.push_str(&format!("\n...and {} more", digest.omitted_cnt));
+        }
+        let embed = CreateEmbed::default()
+            .title("Highlight digest")
+            .description(util::ellipsis_text(&description, 4000));
+        dm_queue.send(digest.user_id, embed.into_create_message());
+    }
+    Ok(())
+}
+
+async fn get_highlight_state(ctx: &client::Context) -> Result<Arc<HighlightState>> {
+    ctx.data.read().await.get::<HighlightState>().cloned().context("No highlight state set")
+}
@@ -0,0 +1,165 @@
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use itertools::Itertools;
+use robbb_commands::{
+    checks::{self, PermissionLevel},
+    commands, modlog,
+};
+use robbb_db::mod_action::ModActionType;
+
+use super::handle_blocklist::{autodelete_message, AutodeleteReason};
+use super::*;
+
+/// Start of the note added for every deleted invite, used to find previous offenses.
+const INVITE_NOTE_PREFIX: &str = "Message deleted because of invite";
+
+/// How far back previous offenses are taken into account when escalating.
+const INVITE_OFFENSE_WINDOW_DAYS: i64 = 30;
+
+/// How many invites of a single message get looked up at most. Invites that are still cached don't count towards this.
+const MAX_INVITE_LOOKUPS: usize = 5;
+
+/// How long resolved invites are remembered.
+const INVITE_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Invite codes with when they were resolved and the id and name of the server they lead to.
+type InviteCache = HashMap<String, (Instant, Option<(GuildId, String)>)>;
+
+lazy_static::lazy_static! {
+    static ref INVITE_CACHE: parking_lot::Mutex<InviteCache> = parking_lot::Mutex::new(HashMap::new());
+}
+
+/// If the message contains an invite to a server that isn't ours or on the invite allowlist,
+/// delete the message, notify the user and #bot-auto-mod.
+/// Repeat offenders get muted, for longer the more often they've done it.
+/// Messages with too many invites to look up are deleted as well, but don't count as an offense.
+/// Returns true if the message was deleted, in which case handling the message_create event should be stopped early.
+#[tracing::instrument(skip_all, fields(invite_filter.code, msg.author = %msg.author.tag(), %msg.id))]
+pub async fn handle_invite_filter(ctx: &client::Context, msg: &Message) -> Result<bool> {
+    if msg.guild_id.is_none() {
+        return Ok(false);
+    }
+    let codes = util::find_invite_codes(&msg.content).into_iter().unique().collect_vec();
+    if codes.is_empty() {
+        return Ok(false);
+    }
+    if checks::get_permission_level(&ctx, &msg.author).await? == PermissionLevel::Mod {
+        return Ok(false);
+    }
+
+    let (config, db) = ctx.get_config_and_db().await;
+    let allowlist = db.get_invite_allowlist().await?;
+
+    let code_cnt = codes.len();
+    let mut lookups = 0;
+    let mut blocked_invite = None;
+    let mut unchecked_invite = None;
+    for code in codes {
+        let guild = match cached_invite_guild(&code) {
+            Some(guild) => guild,
+            None if lookups < MAX_INVITE_LOOKUPS => {
+                lookups += 1;
+                resolve_invite_guild(ctx, &code).await
+            }
+            // Resolving every single one would use up our rate limit, so don't even try
+            None => {
+                unchecked_invite.get_or_insert(code);
+                continue;
+            }
+        };
+        let is_allowed = guild.as_ref().is_some_and(|(guild_id, _)| {
+            *guild_id == config.guild || allowlist.iter().any(|x| x.guild_id == *guild_id)
+        });
+        if !is_allowed {
+            blocked_invite = Some((code, guild.map(|(_, name)| name)));
+            break;
+        }
+    }
+
+    let Some((code, guild_name)) = blocked_invite else {
+        if let Some(code) = unchecked_invite {
+            // We can't tell whether these are fine, so remove them without holding it against the author
+            tracing::info!(invite_filter.code = %code, "Found too many invites to check");
+            tracing::Span::current().record("invite_filter.code", code.as_str());
+            let reason = AutodeleteReason {
+                dm_reason: "containing too many invites".to_string(),
+                cause: format!("{code_cnt} invites, i.e. `{code}`"),
+                note: format!("Message deleted because of {code_cnt} invites, i.e. `{code}`"),
+            };
+            autodelete_message(ctx, msg, reason).await?;
+            return Ok(true);
+        }
+        return Ok(false);
+    };
+    tracing::info!(invite_filter.code = %code, "Found invite to another server");
+    tracing::Span::current().record("invite_filter.code", code.as_str());
+
+    let offense_cutoff = Utc::now() - chrono::Duration::days(INVITE_OFFENSE_WINDOW_DAYS);
+    let previous_offenses = db
+        .get_mod_actions(msg.author.id, Some(ModActionType::BlocklistViolation))
+        .await?
+        .iter()
+        .filter(|x| x.reason.starts_with(INVITE_NOTE_PREFIX))
+        .filter(|x| x.create_date.is_some_and(|date| date > offense_cutoff))
+        .count();
+
+    let server = guild_name.map(|x| format!(" to `{x}`")).unwrap_or_default();
+    let reason = AutodeleteReason {
+        dm_reason: "containing an invite to another server".to_string(),
+        cause: format!("Invite `{code}`{server}"),
+        note: format!("{INVITE_NOTE_PREFIX} `{code}`{server}"),
+    };
+    autodelete_message(ctx, msg, reason).await?;
+
+    let mute_duration = match previous_offenses {
+        0 => None,
+        1 => Some(std::time::Duration::from_secs(60 * 60)),
+        _ => Some(std::time::Duration::from_secs(60 * 60 * 24)),
+    };
+    if let Some(duration) = mute_duration {
+        let member = config.guild.member(&ctx, msg.author.id).await?;
+        let bot_id = ctx.cache.current_user().id;
+        commands::mute::apply_mute(
+            ctx,
+            bot_id,
+            member,
+            duration,
+            Some(format!(
+                "[AUTO] repeatedly posting invites to other servers ({} times in {INVITE_OFFENSE_WINDOW_DAYS} days)",
+                previous_offenses + 1
+            )),
+            msg.link(),
+        )
+        .await?;
+        modlog::log_automute(ctx, msg, duration, "repeatedly posting invites to other servers")
+            .await;
+    }
+
+    Ok(true)
+}
+
+/// Get the server an invite leads to from the cache, if it was looked up recently.
+fn cached_invite_guild(code: &str) -> Option<Option<(GuildId, String)>> {
+    let cache = INVITE_CACHE.lock();
+    let (resolved_at, guild) = cache.get(code)?;
+    (resolved_at.elapsed() < INVITE_CACHE_TTL).then(|| guild.clone())
+}
+
+/// Look up which server an invite leads to, as its id and name.
+/// Invites that can't be resolved can't be verified to be ours, so they get treated as foreign ones.
+/// Results are cached for a while, so the same invite being posted repeatedly doesn't cost a request every time.
+async fn resolve_invite_guild(ctx: &client::Context, code: &str) -> Option<(GuildId, String)> {
+    let guild = match ctx.http.get_invite(code, false, false, None).await {
+        Ok(invite) => invite.guild.map(|x| (x.id, x.name)),
+        Err(err) => {
+            tracing::info!(error.message = %err, invite.code = %code, "Failed to resolve invite");
+            None
+        }
+    };
+    let now = Instant::now();
+    let mut cache = INVITE_CACHE.lock();
+    cache.retain(|_, (resolved_at, _)| now - *resolved_at < INVITE_CACHE_TTL);
+    cache.insert(code.to_string(), (now, guild.clone()));
+    guild
+}
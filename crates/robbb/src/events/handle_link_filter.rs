@@ -0,0 +1,96 @@
+use chrono::Utc;
+use itertools::Itertools;
+use robbb_commands::checks::{self, PermissionLevel};
+use robbb_db::domain_filter::DomainFilterKind;
+
+use super::handle_blocklist::{autodelete_message, AutodeleteReason};
+use super::*;
+
+lazy_static::lazy_static! {
+    static ref URL_REGEX: regex::Regex = regex::Regex::new(r"(?i)\bhttps?://[^\s<>]+").unwrap();
+}
+
+/// Collect the hosts of all links in the message content and embeds.
+pub fn extract_link_hosts(msg: &Message) -> Vec<String> {
+    let embed_urls = msg.embeds.iter().filter_map(|embed| embed.url.as_deref());
+    URL_REGEX
+        .find_iter(&msg.content)
+        .map(|m| m.as_str())
+        .chain(embed_urls)
+        .filter_map(|link| url::Url::parse(link).ok())
+        .filter_map(|url| url.host_str().map(|host| host.trim_end_matches('.').to_lowercase()))
+        .unique()
+        .collect()
+}
+
+/// Check if the author of the message joined recently enough to fall under the new member link policy.
+async fn is_new_member(ctx: &client::Context, msg: &Message, hours: i64) -> Result<bool> {
+    let config = ctx.get_config().await;
+    let joined_at = match msg.member.as_ref().and_then(|x| x.joined_at) {
+        Some(joined_at) => Some(joined_at),
+        None => config.guild.member(&ctx, msg.author.id).await?.joined_at,
+    };
+    Ok(joined_at.is_some_and(|joined_at| {
+        Utc::now().signed_duration_since(*joined_at) < chrono::Duration::hours(hours)
+    }))
+}
+
+/// If the message contains a link to a denied domain, or a link at all while the author is a new member,
+/// delete the message, notify the user and #bot-auto-mod.
+/// Allowed domains are exempt from the new member policy.
+/// Returns true if the message was deleted, in which case handling the message_create event should be stopped early.
+#[tracing::instrument(skip_all, fields(link_filter.host, msg.author = %msg.author.tag(), %msg.id))]
+pub async fn handle_link_filter(ctx: &client::Context, msg: &Message) -> Result<bool> {
+    if msg.guild_id.is_none() {
+        return Ok(false);
+    }
+    let hosts = extract_link_hosts(msg);
+    if hosts.is_empty() {
+        return Ok(false);
+    }
+
+    let (config, db) = ctx.get_config_and_db().await;
+    let domain_filter = db.get_domain_filter().await?;
+
+    let mut unlisted_host = None;
+    let mut reason = None;
+    for host in &hosts {
+        match domain_filter.check_host(host) {
+            Some(entry) if entry.kind == DomainFilterKind::Deny => {
+                reason = Some(AutodeleteReason {
+                    dm_reason: format!("containing a link to a blocked domain: `{host}`"),
+                    cause: format!("Link to `{host}` (matched `{}`)", entry.domain),
+                    note: format!("Message deleted because of link to `{host}`"),
+                });
+                tracing::Span::current().record("link_filter.host", host.as_str());
+                break;
+            }
+            Some(_) => {}
+            None => unlisted_host = unlisted_host.or(Some(host)),
+        }
+    }
+
+    if reason.is_none() {
+        if let (Some(host), Some(hours)) = (unlisted_host, config.link_filter_new_member_hours) {
+            if is_new_member(ctx, msg, hours).await? {
+                reason = Some(AutodeleteReason {
+                    dm_reason: format!(
+                        "containing a link. New members may not post links for {hours} hours after joining"
+                    ),
+                    cause: format!("Link to `{host}` by a new member"),
+                    note: format!("Message deleted because of link to `{host}` as a new member"),
+                });
+                tracing::Span::current().record("link_filter.host", host.as_str());
+            }
+        }
+    }
+
+    let Some(reason) = reason else { return Ok(false) };
+    if checks::get_permission_level(&ctx, &msg.author).await? == PermissionLevel::Mod {
+        return Ok(false);
+    }
+
+    tracing::info!(link_filter.cause = %reason.cause, "Deleting message because of link filter");
+    autodelete_message(ctx, msg, reason).await?;
+    Ok(true)
+}
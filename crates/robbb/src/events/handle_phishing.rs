@@ -0,0 +1,444 @@
+use std::collections::HashSet;
+
+use chrono::Utc;
+use itertools::Itertools;
+use robbb_commands::{
+    checks::{self, PermissionLevel},
+    commands,
+};
+use serenity::{
+    all::{ButtonStyle, ComponentInteraction},
+    builder::{CreateActionRow, CreateButton, CreateEmbedAuthor, CreateMessage},
+};
+
+use robbb_util::embeds;
+
+use super::handle_link_filter::extract_link_hosts;
+use super::*;
+
+/// Prefix of the custom id of the ban button in the modlog entry, followed by the user id.
+pub const PHISHING_BAN_BUTTON: &str = "phishing-ban:";
+/// Prefix of the custom id of the false-positive button in the modlog entry, followed by the user id.
+pub const PHISHING_FALSE_POSITIVE_BUTTON: &str = "phishing-false-positive:";
+
+/// How long users posting phishing links get muted for, until a mod takes a look.
+const PHISHING_MUTE_DURATION: std::time::Duration = std::time::Duration::from_secs(60 * 60 * 24);
+
+/// Reason prefix of the mutes applied by the phishing detection, used to tell them apart from mutes applied by moderators.
+const PHISHING_MUTE_REASON_PREFIX: &str = "[AUTO] Suspected phishing: ";
+
+/// How far back messages of a caught user get deleted.
+const PHISHING_CLEANUP_MINUTES: i64 = 60;
+
+/// Domains commonly impersonated by phishing links, together with their legitimate domains.
+static IMPERSONATED_BRANDS: &[(&str, &[&str])] = &[
+    (
+        "discord",
+        &[
+            "discord.com",
+            "discord.gg",
+            "discordapp.com",
+            "discordapp.net",
+            "discord.media",
+            "discord.new",
+            "discord.gift",
+            "discordstatus.com",
+        ],
+    ),
+    ("discordapp", &["discordapp.com", "discordapp.net"]),
+    ("steamcommunity", &["steamcommunity.com"]),
+    ("steampowered", &["steampowered.com"]),
+];
+
+/// Words that show up in phishing domains as their own label or next to a brand name, i.e. `discord-nitro.gift`.
+static SCAM_WORDS: &[&str] = &["nitro", "gift", "free", "promo", "airdrop", "giveaway", "drop"];
+
+/// How sure we are that a host imitates one of the commonly impersonated brands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LookalikeMatch {
+    /// Only homoglyphs were swapped, or there's more than just a similar name pointing towards a scam.
+    Certain,
+    /// The name is similar, but that might just as well be a legitimate site.
+    Possible,
+}
+
+#[derive(Debug)]
+enum PhishingReason {
+    KnownDomain(String),
+    Lookalike { host: String, brand: &'static str },
+    PossibleLookalike { host: String, brand: &'static str },
+    EveryoneWithLink(String),
+}
+
+impl PhishingReason {
+    /// Whether the author should be quarantined, rather than just letting the mods know.
+    fn is_certain(&self) -> bool {
+        !matches!(self, PhishingReason::PossibleLookalike { .. })
+    }
+}
+
+impl std::fmt::Display for PhishingReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PhishingReason::KnownDomain(host) => write!(f, "Known phishing domain `{host}`"),
+            PhishingReason::Lookalike { host, brand } => {
+                write!(f, "`{host}` looks like an imitation of {brand}")
+            }
+            PhishingReason::PossibleLookalike { host, brand } => {
+                write!(f, "`{host}` might be an imitation of {brand}")
+            }
+            PhishingReason::EveryoneWithLink(host) => {
+                write!(f, "Mentions everyone together with a link to `{host}`")
+            }
+        }
+    }
+}
+
+/// If the message looks like a scam, delete the recent messages of the author in all channels,
+/// mute them and post a modlog entry that lets mods ban them or mark this as a false positive.
+/// Messages that only might be a scam are reported to #bot-auto-mod instead.
+/// Returns true if the message was handled, in which case handling the message_create event should be stopped early.
+#[tracing::instrument(skip_all, fields(phishing.reason, msg.author = %msg.author.tag(), %msg.id))]
+pub async fn handle_phishing(ctx: &client::Context, msg: &Message) -> Result<bool> {
+    if msg.guild_id.is_none() {
+        return Ok(false);
+    }
+    let hosts = extract_link_hosts(msg);
+    if hosts.is_empty() {
+        return Ok(false);
+    }
+
+    let db = ctx.get_db().await;
+    let phishing_domains = db.get_phishing_domains().await?;
+    let Some(reason) = detect_phishing(msg, &hosts, &phishing_domains) else { return Ok(false) };
+
+    if checks::get_permission_level(&ctx, &msg.author).await? != PermissionLevel::User {
+        return Ok(false);
+    }
+    tracing::info!(phishing.reason = %reason, "Detected phishing message");
+    tracing::Span::current().record("phishing.reason", reason.to_string().as_str());
+
+    if !reason.is_certain() {
+        ctx.log_automod_action(|e| {
+            e.author_user(&msg.author)
+                .title("Possible phishing")
+                .description(format!("{}\n{}", msg.author.mention(), msg.to_context_link()))
+                .field("Reason", reason.to_string(), false)
+                .field("Message", util::ellipsis_text(&msg.content, 1000), false)
+        })
+        .await;
+        return Ok(false);
+    }
+
+    quarantine_user(ctx, msg, &reason).await?;
+    Ok(true)
+}
+
+fn detect_phishing(
+    msg: &Message,
+    hosts: &[String],
+    phishing_domains: &HashSet<String>,
+) -> Option<PhishingReason> {
+    if let Some(host) = hosts.iter().find(|host| is_listed(host, phishing_domains)) {
+        return Some(PhishingReason::KnownDomain(host.to_string()));
+    }
+    let lookalikes =
+        hosts.iter().filter_map(|host| Some((host, find_impersonated_brand(host)?))).collect_vec();
+    // Only count actual mass mentions, not just the text, which anyone can type
+    let certain_lookalike = lookalikes
+        .iter()
+        .find(|(_, (_, strength))| msg.mention_everyone || *strength == LookalikeMatch::Certain);
+    if let Some((host, (brand, _))) = certain_lookalike {
+        return Some(PhishingReason::Lookalike { host: host.to_string(), brand });
+    }
+    if msg.mention_everyone {
+        return Some(PhishingReason::EveryoneWithLink(hosts[0].to_string()));
+    }
+    if let Some((host, (brand, _))) = lookalikes.first() {
+        return Some(PhishingReason::PossibleLookalike { host: host.to_string(), brand });
+    }
+    None
+}
+
+/// Check if the host or any of its parent domains is on the list.
+fn is_listed(host: &str, domains: &HashSet<String>) -> bool {
+    let mut rest = host;
+    loop {
+        if domains.contains(rest) {
+            return true;
+        }
+        match rest.split_once('.') {
+            Some((_, parent)) if parent.contains('.') => rest = parent,
+            _ => return false,
+        }
+    }
+}
+
+/// Check if the host imitates one of the commonly impersonated brands without being one of their domains.
+fn find_impersonated_brand(host: &str) -> Option<(&'static str, LookalikeMatch)> {
+    let is_legit = IMPERSONATED_BRANDS
+        .iter()
+        .flat_map(|(_, domains)| domains.iter())
+        .any(|x| host == *x || host.ends_with(&format!(".{x}")));
+    if is_legit {
+        return None;
+    }
+
+    // i.e. `discord.com.example.ru`
+    let legit_prefix_brand = IMPERSONATED_BRANDS.iter().find(|(_, legit_domains)| {
+        legit_domains.iter().any(|x| host.starts_with(&format!("{x}.")))
+    });
+    if let Some((brand, _)) = legit_prefix_brand {
+        return Some((*brand, LookalikeMatch::Certain));
+    }
+
+    // The label right before the top level domain, i.e. `dlscord` in `nitro.dlscord.com`
+    let mut labels = host.rsplit('.');
+    let _tld = labels.next()?;
+    let label = labels.next()?;
+    // The brand itself on another top level domain, i.e. `discord.me`, is most likely a legitimate site
+    if IMPERSONATED_BRANDS.iter().any(|(brand, _)| label == *brand) {
+        return None;
+    }
+    let name = normalize_homoglyphs(label);
+    let has_scam_word = host.split(['.', '-']).any(|x| SCAM_WORDS.contains(&x));
+
+    IMPERSONATED_BRANDS.iter().find_map(|(brand, _)| {
+        let normalized_brand = normalize_homoglyphs(brand);
+        if name == normalized_brand {
+            return Some((*brand, LookalikeMatch::Certain));
+        }
+        let max_distance = if brand.len() >= 10 { 2 } else { 1 };
+        let is_similar = levenshtein(&name, &normalized_brand) <= max_distance
+            || name.contains(&normalized_brand);
+        if !is_similar {
+            return None;
+        }
+        let strength =
+            if has_scam_word { LookalikeMatch::Certain } else { LookalikeMatch::Possible };
+        Some((*brand, strength))
+    })
+}
+
+/// Replace characters commonly used to imitate other characters in domains,
+/// such that imitations end up equal to the original.
+fn normalize_homoglyphs(s: &str) -> String {
+    s.replace("rn", "m")
+        .replace("vv", "w")
+        .replace("cl", "d")
+        .chars()
+        .filter(|c| *c != '-')
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | 'i' => 'l',
+            '3' => 'e',
+            '4' => 'a',
+            '5' => 's',
+            '7' => 't',
+            c => c,
+        })
+        .collect()
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+/// Delete the recent messages of the author in all channels, mute them and create the modlog entry.
+async fn quarantine_user(
+    ctx: &client::Context,
+    msg: &Message,
+    reason: &PhishingReason,
+) -> Result<()> {
+    let config = ctx.get_config().await;
+
+    let cutoff = Utc::now() - chrono::Duration::minutes(PHISHING_CLEANUP_MINUTES);
+    let mut msgs_by_channel = std::collections::HashMap::<ChannelId, Vec<MessageId>>::new();
+    msgs_by_channel.entry(msg.channel_id).or_default().push(msg.id);
+    let channel_ids =
+        ctx.cache.guild(config.guild).map(|x| x.channels.keys().copied().collect_vec());
+    for channel_id in channel_ids.unwrap_or_default() {
+        let Some(msgs) = ctx.cache.channel_messages(channel_id) else { continue };
+        let recent_msgs = msgs
+            .values()
+            .filter(|x| x.author.id == msg.author.id && x.id != msg.id && *x.timestamp > cutoff);
+        msgs_by_channel.entry(channel_id).or_default().extend(recent_msgs.map(|x| x.id));
+    }
+    let mut deleted_cnt = 0;
+    for (channel_id, msg_ids) in msgs_by_channel {
+        for chunk in msg_ids.chunks(100).filter(|x| !x.is_empty()) {
+            match channel_id.delete_messages(&ctx, chunk).await {
+                Ok(()) => deleted_cnt += chunk.len(),
+                Err(err) => {
+                    tracing::error!(error.message = %err, "Failed to delete phishing messages")
+                }
+            }
+        }
+    }
+
+    let member = config.guild.member(&ctx, msg.author.id).await?;
+    let bot_id = ctx.cache.current_user().id;
+    commands::mute::apply_mute(
+        ctx,
+        bot_id,
+        member,
+        PHISHING_MUTE_DURATION,
+        Some(format!("{PHISHING_MUTE_REASON_PREFIX}{reason}")),
+        msg.link(),
+    )
+    .await?;
+
+    let user_id = msg.author.id;
+    let embed = embeds::base_embed_ctx(ctx)
+        .await
+        .author(CreateEmbedAuthor::new("Suspected phishing").icon_url(msg.author.face()))
+        .title(msg.author.name_with_disc_and_id())
+        .description(format!(
+            "{} was muted for {} and {deleted_cnt} of their recent messages were deleted.\n{}",
+            msg.author.mention(),
+            humantime::Duration::from(PHISHING_MUTE_DURATION),
+            msg.to_context_link(),
+        ))
+        .field("Reason", reason.to_string(), false)
+        .field("Message", util::ellipsis_text(&msg.content, 1000), false);
+    let buttons = CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{PHISHING_BAN_BUTTON}{user_id}"))
+            .label("Ban")
+            .style(ButtonStyle::Danger),
+        CreateButton::new(format!("{PHISHING_FALSE_POSITIVE_BUTTON}{user_id}"))
+            .label("False positive")
+            .style(ButtonStyle::Secondary),
+    ]);
+    config
+        .channel_modlog
+        .send_message(&ctx, CreateMessage::default().embed(embed).components(vec![buttons]))
+        .await?;
+    Ok(())
+}
+
+/// Handle a click on the ban button of a phishing modlog entry.
+pub async fn handle_phishing_ban_button(
+    ctx: &client::Context,
+    interaction: &ComponentInteraction,
+    user_id: UserId,
+) -> Result<String> {
+    let config = ctx.get_config().await;
+    let user = user_id.to_user(&ctx).await?;
+    commands::ban::ban_user(
+        ctx,
+        config.guild,
+        interaction.user.id,
+        &user,
+        "Posting phishing links",
+        1,
+        interaction.message.link(),
+    )
+    .await?;
+    Ok(format!("Banned by {}", interaction.user.mention()))
+}
+
+/// Handle a click on the false-positive button of a phishing modlog entry, lifting the mute again.
+pub async fn handle_phishing_false_positive_button(
+    ctx: &client::Context,
+    interaction: &ComponentInteraction,
+    user_id: UserId,
+) -> Result<String> {
+    let (config, db) = ctx.get_config_and_db().await;
+    // Only lift the quarantine itself, not a mute a moderator applied in the meantime
+    let quarantine = db
+        .get_active_mute(user_id)
+        .await?
+        .filter(|mute| mute.reason.starts_with(PHISHING_MUTE_REASON_PREFIX));
+    let Some(quarantine) = quarantine else {
+        return Ok(format!(
+            "Marked as false positive by {}, the user is no longer quarantined",
+            interaction.user.mention()
+        ));
+    };
+    db.set_mute_inactive(quarantine.id).await?;
+    let mut member = config.guild.member(&ctx, user_id).await?;
+    log_error!(member.remove_roles(&ctx, &[config.role_mute]).await);
+    log_error!(member.enable_communication(&ctx).await);
+    Ok(format!("Marked as false positive by {}, mute lifted", interaction.user.mention()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("discord", "discord"), 0);
+        assert_eq!(levenshtein("discords", "discord"), 1);
+        assert_eq!(levenshtein("dicsord", "discord"), 2);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_normalize_homoglyphs() {
+        assert_eq!(normalize_homoglyphs("discord"), "dlscord");
+        assert_eq!(normalize_homoglyphs("d1sc0rd"), "dlscord");
+        assert_eq!(normalize_homoglyphs("disc-ord"), "dlscord");
+        assert_eq!(normalize_homoglyphs("steamcornmunity"), "steamcommunlty");
+        assert_eq!(normalize_homoglyphs("vvumpus"), "wumpus");
+    }
+
+    #[test]
+    fn test_find_impersonated_brand_legit_hosts() {
+        for host in [
+            "discord.com",
+            "cdn.discordapp.com",
+            "media.discordapp.net",
+            "discord.me",
+            "discord.io",
+            "discord.dev",
+            "steamcommunity.ru",
+            "github.com",
+            "freedrop.com",
+        ] {
+            assert_eq!(find_impersonated_brand(host), None, "{}", host);
+        }
+    }
+
+    #[test]
+    fn test_find_impersonated_brand_possible() {
+        for host in ["discords.com", "discordnitro.com", "steamcomunity.com"] {
+            assert!(
+                matches!(find_impersonated_brand(host), Some((_, LookalikeMatch::Possible))),
+                "{}",
+                host
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_impersonated_brand_certain() {
+        for (host, brand) in [
+            ("dlscord.com", "discord"),
+            ("d1sc0rd.gift", "discord"),
+            ("discord-nitro.gift", "discord"),
+            ("free.discords.ru", "discord"),
+            ("discord.com.example.ru", "discord"),
+            ("discord.com.discord.ru", "discord"),
+            ("steamcornmunity.com", "steamcommunity"),
+        ] {
+            assert_eq!(
+                find_impersonated_brand(host),
+                Some((brand, LookalikeMatch::Certain)),
+                "{}",
+                host
+            );
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use chrono::Utc;
+use robbb_commands::commands::{self, tag::tag_proposal_embed};
+use robbb_db::{
+    tag::Tag,
+    tag_proposal::{TagProposal, TagProposalStatus},
+};
+use robbb_util::{cdn_hack, embeds, tag_template};
+use serenity::{
+    all::{ComponentInteraction, CreateQuickModal, InputTextStyle},
+    builder::{
+        CreateInputText, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+    },
+};
+
+use super::component_interaction::respond_ephemeral;
+use super::*;
+
+/// How long moderators get to edit a proposal once the modal is open.
+const EDIT_MODAL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60 * 10);
+
+/// Load a proposal, telling the moderator if it was already resolved in the meantime.
+async fn get_pending_proposal(
+    ctx: &client::Context,
+    interaction: &ComponentInteraction,
+    id: i64,
+) -> Result<Option<TagProposal>> {
+    let db = ctx.get_db().await;
+    let proposal = db.get_tag_proposal(id).await?.context("Unknown tag proposal")?;
+    if proposal.status != TagProposalStatus::Pending {
+        let message =
+            format!("This proposal was already {}", proposal.status.to_string().to_lowercase());
+        respond_ephemeral(ctx, interaction, &message).await?;
+        return Ok(None);
+    }
+    Ok(Some(proposal))
+}
+
+/// Handle a click on one of the approve buttons of a tag proposal, turning it into a tag.
+pub async fn handle_tag_proposal_approve_button(
+    ctx: &client::Context,
+    interaction: &ComponentInteraction,
+    id: i64,
+    official: bool,
+) -> Result<Option<String>> {
+    let db = ctx.get_db().await;
+    let Some(proposal) = get_pending_proposal(ctx, interaction, id).await? else { return Ok(None) };
+
+    if db.get_tag(&proposal.name).await?.is_some() {
+        let message = "A tag with this name already exists, edit the proposal to rename it";
+        respond_ephemeral(ctx, interaction, message).await?;
+        return Ok(None);
+    }
+    if !db.resolve_tag_proposal(id, TagProposalStatus::Approved, interaction.user.id).await? {
+        respond_ephemeral(ctx, interaction, "This proposal was already resolved").await?;
+        return Ok(None);
+    }
+    db.set_tag(
+        &Tag {
+            name: proposal.name.clone(),
+            moderator: proposal.user,
+            content: proposal.content,
+            official,
+            create_date: Some(Utc::now()),
+            category: None,
+            embed: None,
+        },
+        interaction.user.id,
+    )
+    .await?;
+
+    ctx.get_dm_queue().await.send(
+        proposal.user,
+        CreateMessage::default().content(format!(
+            "Your tag proposal `{}` was approved, it's now available via /tag!",
+            proposal.name
+        )),
+    );
+
+    let kind = if official { "an official" } else { "a community" };
+    Ok(Some(format!("Approved as {kind} tag by {}", interaction.user.mention())))
+}
+
+/// Handle a click on the reject button of a tag proposal.
+pub async fn handle_tag_proposal_reject_button(
+    ctx: &client::Context,
+    interaction: &ComponentInteraction,
+    id: i64,
+) -> Result<Option<String>> {
+    let db = ctx.get_db().await;
+    let Some(proposal) = get_pending_proposal(ctx, interaction, id).await? else { return Ok(None) };
+    if !db.resolve_tag_proposal(id, TagProposalStatus::Rejected, interaction.user.id).await? {
+        respond_ephemeral(ctx, interaction, "This proposal was already resolved").await?;
+        return Ok(None);
+    }
+
+    ctx.get_dm_queue().await.send(
+        proposal.user,
+        CreateMessage::default()
+            .content(format!("Your tag proposal `{}` was not accepted.", proposal.name)),
+    );
+    Ok(Some(format!("Rejected by {}", interaction.user.mention())))
+}
+
+/// Handle a click on the edit button of a tag proposal,
+/// letting the moderator change its name and content before approving it.
+/// Responds to the interaction itself, and thus never returns a resolution.
+pub async fn handle_tag_proposal_edit_button(
+    ctx: &client::Context,
+    interaction: &ComponentInteraction,
+    id: i64,
+) -> Result<Option<String>> {
+    let db = ctx.get_db().await;
+    let Some(proposal) = get_pending_proposal(ctx, interaction, id).await? else { return Ok(None) };
+
+    let modal = CreateQuickModal::new("Edit tag proposal")
+        .timeout(EDIT_MODAL_TIMEOUT)
+        .field(
+            CreateInputText::new(InputTextStyle::Short, "Name", "")
+                .value(&proposal.name)
+                .max_length(50),
+        )
+        .field(
+            CreateInputText::new(InputTextStyle::Paragraph, "Content", "").value(&proposal.content),
+        );
+    let Some(response) = interaction.quick_modal(ctx, modal).await? else { return Ok(None) };
+    let [name, content] = response.inputs.as_slice() else {
+        anyhow::bail!("Unexpected number of inputs in tag proposal edit modal");
+    };
+    let name = name.trim();
+
+    let validation = commands::tag::validate_tag_proposal_name(name)
+        .map_err(|e| e.to_string())
+        .and_then(|_| tag_template::validate(content).map_err(|e| e.to_string()));
+    if let Err(err) = validation {
+        let message = CreateInteractionResponseMessage::default().content(err).ephemeral(true);
+        response
+            .interaction
+            .create_response(&ctx, CreateInteractionResponse::Message(message))
+            .await?;
+        return Ok(None);
+    }
+
+    let content = cdn_hack::persist_cdn_links_in_string(
+        ctx,
+        content,
+        serde_json::json!({"kind": "tag", "tag_name": name}),
+    )
+    .await?;
+    if !db.edit_tag_proposal(id, name, &content).await? {
+        let message = CreateInteractionResponseMessage::default()
+            .content("This proposal was already resolved")
+            .ephemeral(true);
+        response
+            .interaction
+            .create_response(&ctx, CreateInteractionResponse::Message(message))
+            .await?;
+        return Ok(None);
+    }
+
+    let proposal = TagProposal { name: name.to_string(), content, ..proposal };
+    let embed = tag_proposal_embed(embeds::base_embed_ctx(ctx).await, &proposal).field(
+        "Last edited by",
+        interaction.user.mention().to_string(),
+        true,
+    );
+    let message = CreateInteractionResponseMessage::default().embed(embed);
+    response
+        .interaction
+        .create_response(&ctx, CreateInteractionResponse::UpdateMessage(message))
+        .await?;
+    Ok(None)
+}
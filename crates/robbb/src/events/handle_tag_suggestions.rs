@@ -0,0 +1,157 @@
+use chrono::Utc;
+use robbb_commands::{
+    checks::{self, PermissionLevel},
+    commands::tag::{self, TAG_SUGGESTION_DISMISS_BUTTON, TAG_SUGGESTION_SHOW_BUTTON},
+};
+use robbb_util::{embeds, tag_template};
+use serenity::{
+    all::{ButtonStyle, ComponentInteraction},
+    builder::{
+        CreateActionRow, CreateAllowedMentions, CreateButton, CreateInteractionResponse,
+        CreateInteractionResponseMessage, CreateMessage,
+    },
+};
+
+use crate::tag_suggestions::TagSuggestionState;
+
+use super::component_interaction::respond_ephemeral;
+use super::*;
+
+/// Discord doesn't allow custom ids longer than this.
+const MAX_CUSTOM_ID_LEN: usize = 100;
+
+/// Suggest a tag when a message in tech support matches one of the tag triggers.
+/// Returns the name of the suggested tag, if any.
+#[tracing::instrument(skip_all, fields(tag_suggestion.tag))]
+pub async fn handle_tag_suggestions(
+    ctx: &client::Context,
+    msg: &Message,
+) -> Result<Option<String>> {
+    let (config, db) = ctx.get_config_and_db().await;
+    if msg.channel_id != config.channel_tech_support || msg.content.is_empty() {
+        return Ok(None);
+    }
+
+    let triggers = db.get_tag_triggers().await?;
+    let Some(trigger) = triggers.iter().find(|x| x.is_match(&msg.content)) else { return Ok(None) };
+    let Some(tag) = db.get_tag(&trigger.tag).await? else { return Ok(None) };
+
+    let show_id = format!("{TAG_SUGGESTION_SHOW_BUTTON}{}:{}", msg.author.id, tag.name);
+    // Tags that need arguments can only be posted via /tag
+    if tag_template::required_args(&tag.content) > 0 || show_id.len() > MAX_CUSTOM_ID_LEN {
+        return Ok(None);
+    }
+    if !get_tag_suggestion_state(ctx).await?.try_start_cooldown(msg.channel_id, &tag.name) {
+        return Ok(None);
+    }
+    tracing::Span::current().record("tag_suggestion.tag", tag.name.as_str());
+
+    let buttons = CreateActionRow::Buttons(vec![
+        CreateButton::new(show_id).label("Show tag").style(ButtonStyle::Primary),
+        CreateButton::new(format!("{TAG_SUGGESTION_DISMISS_BUTTON}{}", msg.author.id))
+            .label("Dismiss")
+            .style(ButtonStyle::Secondary),
+    ]);
+    msg.channel_id
+        .send_message(
+            &ctx,
+            CreateMessage::default()
+                .content(format!("This might help: `{}`", tag.name))
+                .reference_message(msg)
+                .allowed_mentions(CreateAllowedMentions::new())
+                .components(vec![buttons]),
+        )
+        .await?;
+    Ok(Some(tag.name))
+}
+
+/// Handle a click on one of the buttons of a tag suggestion.
+/// Returns false if the interaction doesn't belong to a tag suggestion.
+pub async fn handle_tag_suggestion_button(
+    ctx: &client::Context,
+    interaction: &ComponentInteraction,
+) -> Result<bool> {
+    let custom_id = interaction.data.custom_id.as_str();
+    if let Some(payload) = custom_id.strip_prefix(TAG_SUGGESTION_SHOW_BUTTON) {
+        let (asker, tag_name) = payload.split_once(':').context("Malformed tag suggestion id")?;
+        let asker = asker.parse::<UserId>().context("Malformed tag suggestion id")?;
+        handle_show_button(ctx, interaction, asker, tag_name).await?;
+        Ok(true)
+    } else if let Some(asker) = custom_id.strip_prefix(TAG_SUGGESTION_DISMISS_BUTTON) {
+        let asker = asker.parse::<UserId>().context("Malformed tag suggestion id")?;
+        handle_dismiss_button(ctx, interaction, asker).await?;
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// Replace the suggestion with the tag itself, addressed to the user that asked.
+async fn handle_show_button(
+    ctx: &client::Context,
+    interaction: &ComponentInteraction,
+    asker: UserId,
+    tag_name: &str,
+) -> Result<()> {
+    let db = ctx.get_db().await;
+    let Some(tag) = db.get_tag(tag_name).await? else {
+        respond_ephemeral(ctx, interaction, "This tag doesn't exist anymore").await?;
+        return Ok(());
+    };
+
+    let template_ctx = tag_template::TagTemplateContext {
+        user: Some(asker),
+        author: interaction.user.id,
+        channel: interaction.channel_id,
+        args: Vec::new(),
+    };
+    let base_embed = embeds::base_embed_ctx(ctx).await;
+    let message = match tag::render_tag(ctx, base_embed, &tag, &template_ctx).await {
+        Ok(message) => message,
+        Err(err) => {
+            respond_ephemeral(ctx, interaction, "This tag can't be shown here, use /tag instead")
+                .await?;
+            return Err(err);
+        }
+    };
+
+    let response = CreateInteractionResponseMessage::default()
+        .content(message.content.unwrap_or_default())
+        .embeds(message.embeds)
+        .components(vec![])
+        .allowed_mentions(CreateAllowedMentions::new().users(vec![asker]));
+    interaction.create_response(&ctx, CreateInteractionResponse::UpdateMessage(response)).await?;
+
+    log_error!(
+        db.record_tag_usage(&tag.name, interaction.user.id, interaction.channel_id, Utc::now())
+            .await
+    );
+    Ok(())
+}
+
+/// Delete the suggestion. Only the user that asked and moderators may do this.
+async fn handle_dismiss_button(
+    ctx: &client::Context,
+    interaction: &ComponentInteraction,
+    asker: UserId,
+) -> Result<()> {
+    if interaction.user.id != asker
+        && checks::get_permission_level(ctx, &interaction.user).await? != PermissionLevel::Mod
+    {
+        respond_ephemeral(ctx, interaction, "Only the person this was suggested to can dismiss it")
+            .await?;
+        return Ok(());
+    }
+    interaction.create_response(&ctx, CreateInteractionResponse::Acknowledge).await?;
+    interaction.message.delete(&ctx).await?;
+    Ok(())
+}
+
+async fn get_tag_suggestion_state(ctx: &client::Context) -> Result<Arc<TagSuggestionState>> {
+    ctx.data
+        .read()
+        .await
+        .get::<TagSuggestionState>()
+        .cloned()
+        .context("No tag suggestion state set")
+}
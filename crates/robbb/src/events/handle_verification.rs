@@ -0,0 +1,240 @@
+use chrono::Utc;
+use robbb_commands::commands;
+use robbb_db::verification::Verification;
+use robbb_util::config::VerificationConfig;
+use serenity::{
+    all::{ComponentInteraction, CreateQuickModal, HttpError, ModalInteraction},
+    builder::{CreateEmbedAuthor, CreateInteractionResponse, CreateInteractionResponseMessage},
+};
+
+use super::component_interaction::respond_ephemeral;
+use super::*;
+
+/// How long members get to answer the verification question once the modal is open.
+const VERIFICATION_MODAL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60 * 5);
+
+/// How long to wait before trying again to kick a member that couldn't be kicked.
+const VERIFICATION_KICK_RETRY_MINUTES: i64 = 60;
+
+/// Discord error code for members that aren't in the server (anymore).
+const UNKNOWN_MEMBER_ERROR: isize = 10007;
+
+/// Give a member that just joined the unverified role and start tracking their verification, if verification is enabled.
+#[tracing::instrument(skip_all, fields(user.id = %member.user.id))]
+pub async fn start_member_verification(ctx: &client::Context, member: &Member) -> Result<()> {
+    let (config, db) = ctx.get_config_and_db().await;
+    let Some(verification_config) = &config.verification else { return Ok(()) };
+    if member.user.bot {
+        return Ok(());
+    }
+
+    let join_date = member.joined_at.map(|x| *x).unwrap_or_else(Utc::now);
+    db.start_verification(member.user.id, join_date, join_date + verification_config.timeout)
+        .await?;
+    ctx.http
+        .add_member_role(
+            config.guild,
+            member.user.id,
+            verification_config.role_unverified,
+            Some("Joined, not verified yet"),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Handle a member pressing the verification button in the rules channel,
+/// asking them the verification question if one is configured.
+#[tracing::instrument(skip_all, fields(user.id = %interaction.user.id))]
+pub async fn handle_verification_button(
+    ctx: &client::Context,
+    interaction: &ComponentInteraction,
+) -> Result<()> {
+    let (config, db) = ctx.get_config_and_db().await;
+    let Some(verification_config) = &config.verification else {
+        respond_ephemeral(ctx, interaction, "Verification is not enabled").await?;
+        return Ok(());
+    };
+    let user = &interaction.user;
+    let member = match &interaction.member {
+        Some(member) => member.clone(),
+        None => config.guild.member(&ctx, user.id).await?,
+    };
+    if !member.roles.contains(&verification_config.role_unverified) {
+        respond_ephemeral(ctx, interaction, "You're already verified").await?;
+        return Ok(());
+    }
+    // The role is what counts, so members that somehow lost their verification entry can still verify
+    let verification = match db.get_verification(user.id).await? {
+        Some(verification) => verification,
+        None => {
+            let join_date = member.joined_at.map(|x| *x).unwrap_or_else(Utc::now);
+            db.start_verification(user.id, join_date, Utc::now() + verification_config.timeout)
+                .await?;
+            db.get_verification(user.id).await?.context("Failed to start verification")?
+        }
+    };
+
+    let account_age = Utc::now().signed_duration_since(*user.created_at());
+    let allowed_at = verification.join_date + verification_config.young_account_delay;
+    if account_age < verification_config.min_account_age && Utc::now() < allowed_at {
+        log_verification_failure(ctx, user, "Account is too new, asked to wait".to_string()).await;
+        respond_ephemeral(
+            ctx,
+            interaction,
+            &format!(
+                "Your account is quite new, so please take some time to read the rules. You can verify <t:{}:R>.",
+                allowed_at.timestamp()
+            ),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let mut modal_interaction: Option<ModalInteraction> = None;
+    if let (Some(question), Some(answer)) =
+        (&verification_config.question, &verification_config.answer)
+    {
+        let modal = CreateQuickModal::new("Verification")
+            .timeout(VERIFICATION_MODAL_TIMEOUT)
+            .short_field(util::ellipsis_text(question, 45));
+        let Some(response) = interaction.quick_modal(ctx, modal).await? else { return Ok(()) };
+        let given_answer = response.inputs.first().map(|x| x.trim()).unwrap_or_default();
+        if !given_answer.eq_ignore_ascii_case(answer.trim()) {
+            let attempts = db.record_failed_verification(user.id).await?;
+            log_verification_failure(
+                ctx,
+                user,
+                format!(
+                    "Wrong answer `{}` (attempt {attempts})",
+                    util::ellipsis_text(given_answer, 200).replace('`', "'")
+                ),
+            )
+            .await;
+            let message = CreateInteractionResponseMessage::default()
+                .content("That's not quite right. Please read the rules again and try once more.")
+                .ephemeral(true);
+            response
+                .interaction
+                .create_response(&ctx, CreateInteractionResponse::Message(message))
+                .await?;
+            return Ok(());
+        }
+        modal_interaction = Some(response.interaction);
+    }
+
+    ctx.http
+        .remove_member_role(
+            config.guild,
+            user.id,
+            verification_config.role_unverified,
+            Some("Verified"),
+        )
+        .await?;
+    db.set_verified(user.id).await?;
+    tracing::info!("Member verified");
+
+    let message = CreateInteractionResponseMessage::default()
+        .content("Thanks, you're verified. Welcome!")
+        .ephemeral(true);
+    match modal_interaction {
+        Some(modal_interaction) => {
+            modal_interaction
+                .create_response(&ctx, CreateInteractionResponse::Message(message))
+                .await?
+        }
+        None => {
+            interaction.create_response(&ctx, CreateInteractionResponse::Message(message)).await?
+        }
+    }
+    Ok(())
+}
+
+/// Kick all members that didn't verify before their deadline.
+/// Members who lost the unverified role in the meantime, i.e. because a mod removed it, count as verified.
+/// Members that can't be handled right now are tried again on the next run, or an hour later if kicking them failed.
+#[tracing::instrument(skip_all)]
+pub async fn kick_unverified_members(ctx: &client::Context) -> Result<()> {
+    let (config, db) = ctx.get_config_and_db().await;
+    let Some(verification_config) = &config.verification else { return Ok(()) };
+
+    for verification in db.get_expired_verifications().await? {
+        let user_id = verification.user;
+        if let Err(err) = handle_expired_verification(ctx, verification_config, verification).await
+        {
+            tracing::error!(
+                error.message = %err,
+                error = ?err,
+                user.id = %user_id,
+                "Error handling expired verification"
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn handle_expired_verification(
+    ctx: &client::Context,
+    verification_config: &VerificationConfig,
+    verification: Verification,
+) -> Result<()> {
+    let (config, db) = ctx.get_config_and_db().await;
+    let member = match config.guild.member(&ctx, verification.user).await {
+        Ok(member) => member,
+        Err(serenity::Error::Http(HttpError::UnsuccessfulRequest(response)))
+            if response.error.code == UNKNOWN_MEMBER_ERROR =>
+        {
+            db.remove_verification(verification.user).await?;
+            return Ok(());
+        }
+        Err(err) => return Err(err.into()),
+    };
+    if !member.roles.contains(&verification_config.role_unverified) {
+        db.set_verified(verification.user).await?;
+        return Ok(());
+    }
+
+    tracing::info!(user.id = %verification.user, "Kicking member that didn't verify in time");
+    let kick_result = commands::kick::do_kick(
+        ctx,
+        config.guild,
+        &member.user,
+        "You didn't verify in time. Feel free to rejoin and try again.",
+    )
+    .await;
+    if let Err(err) = kick_result {
+        let retry_at = Utc::now() + chrono::Duration::minutes(VERIFICATION_KICK_RETRY_MINUTES);
+        db.postpone_verification(verification.user, retry_at).await?;
+        log_verification_failure(
+            ctx,
+            &member.user,
+            "Couldn't be kicked for not verifying in time, please do it manually".to_string(),
+        )
+        .await;
+        return Err(err);
+    }
+    db.remove_verification(verification.user).await?;
+    log_verification_failure(
+        ctx,
+        &member.user,
+        format!(
+            "Kicked for not verifying within {} ({} failed attempts)",
+            humantime::Duration::from(verification_config.timeout.to_std()?),
+            verification.failed_attempts
+        ),
+    )
+    .await;
+    Ok(())
+}
+
+async fn log_verification_failure(ctx: &client::Context, user: &User, reason: String) {
+    let config = ctx.get_config().await;
+    let result = config
+        .channel_bot_traffic
+        .send_embed_builder(ctx, |e| {
+            e.author(CreateEmbedAuthor::new("Verification failed").icon_url(user.face()))
+                .title(user.name_with_disc_and_id())
+                .description(format!("{}\n{reason}", user.mention()))
+        })
+        .await;
+    log_error!(result);
+}
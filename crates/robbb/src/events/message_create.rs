@@ -1,11 +1,11 @@
-use std::collections::HashSet;
-
-use chrono::Utc;
 use itertools::Itertools;
 use maplit::hashmap;
 use poise::serenity_prelude::{MessageType, ReactionType};
 use regex::Regex;
-use robbb_commands::{commands, modlog};
+use robbb_commands::{
+    checks::{self, PermissionLevel},
+    commands, modlog,
+};
 use robbb_db::emoji_logging::EmojiIdentifier;
 use robbb_db::fetch_field::FetchField;
 
@@ -15,6 +15,7 @@ use tracing::debug;
 use tracing_futures::Instrument;
 
 use crate::attachment_logging;
+use crate::spam_protection::SpamDetector;
 
 use super::*;
 
@@ -42,13 +43,20 @@ pub async fn message_create(ctx: client::Context, msg: Message) -> Result<bool>
 
     handle_attachment_logging(&ctx, &msg).await;
 
+    if Some(msg.channel_id) == config.channel_honeypot {
+        match handle_honeypot(&ctx, &msg).await {
+            Ok(stop) if stop => return Ok(true),
+            err => log_error!("error while handling honeypot message", err),
+        }
+    }
+
     if msg.channel_id == config.channel_showcase {
         log_error!(handle_showcase_post(&ctx, &msg).await);
     } else if msg.channel_id == config.channel_feedback {
         log_error!(handle_feedback_post(&ctx, &msg).await);
     }
 
-    if !msg.guild_id.is_none() && msg.channel_id != config.channel_bot_messages {
+    if msg.guild_id.is_some() && msg.channel_id != config.channel_bot_messages {
         match handle_msg_emoji_logging(&ctx, &msg).await {
             Ok(emoji_used) => {
                 tracing::Span::current().record("message_create.emoji_used", emoji_used);
@@ -57,6 +65,14 @@ pub async fn message_create(ctx: client::Context, msg: Message) -> Result<bool>
         }
     }
 
+    match handle_phishing::handle_phishing(&ctx, &msg).await {
+        Ok(stop) if stop => {
+            tracing::info!("Stopping message handling after phishing detection");
+            return Ok(true);
+        }
+        err => log_error!("error while handling phishing detection", err),
+    };
+
     let (stop_after_spam_protect, stop_after_blocklist) = tokio::join!(
         handle_spam_protect(&ctx, &msg),
         handle_blocklist::handle_blocklist(&ctx, &msg),
@@ -78,8 +94,27 @@ pub async fn message_create(ctx: client::Context, msg: Message) -> Result<bool>
         err => log_error!("error while handling blocklist", err),
     };
 
-    let (highlighting_notified_users, quoting_result) =
-        tokio::join!(handle_highlighting(&ctx, &msg), handle_quote(&ctx, &msg));
+    match handle_invite_filter::handle_invite_filter(&ctx, &msg).await {
+        Ok(stop) if stop => {
+            tracing::info!("Stopping message handling after invite filter");
+            return Ok(true);
+        }
+        err => log_error!("error while handling invite filter", err),
+    };
+
+    match handle_link_filter::handle_link_filter(&ctx, &msg).await {
+        Ok(stop) if stop => {
+            tracing::info!("Stopping message handling after link filter");
+            return Ok(true);
+        }
+        err => log_error!("error while handling link filter", err),
+    };
+
+    let (highlighting_notified_users, quoting_result, tag_suggestion_result) = tokio::join!(
+        handle_highlighting::handle_highlighting(&ctx, &msg),
+        handle_quote(&ctx, &msg),
+        handle_tag_suggestions::handle_tag_suggestions(&ctx, &msg),
+    );
 
     match highlighting_notified_users {
         Ok(notified_users) => {
@@ -89,105 +124,12 @@ pub async fn message_create(ctx: client::Context, msg: Message) -> Result<bool>
     }
 
     log_error!("error while Handling a quoted message", quoting_result);
+    log_error!("error while suggesting tags", tag_suggestion_result);
 
     // If the message is in showcase, don't forward to the command framework
     Ok(msg.channel_id == config.channel_showcase)
 }
 
-#[tracing::instrument(skip_all, fields(highlights.notified_user_cnt))]
-async fn handle_highlighting(ctx: &client::Context, msg: &Message) -> Result<usize> {
-    let (config, db) = ctx.get_config_and_db().await;
-
-    let highlights_data = db.get_highlights().await?;
-
-    let highlight_matches = tokio::task::spawn_blocking({
-        let msg_content = msg.content.to_string();
-        move || highlights_data.get_triggers_for_message(&msg_content)
-    })
-    .instrument(tracing::debug_span!("highlights-trigger-check"))
-    .await
-    .context("Failed to get highlight triggers for a message")?;
-
-    if highlight_matches.is_empty() {
-        tracing::Span::current().record("highlights.notified_user_cnt", 0i32);
-        return Ok(0);
-    }
-    // don't highlight in threads or mod internal channels
-    // We do this after checking for highlights as checking for highlights is a lot
-    // cheaper than potentially sending discord API requests for
-    // a lot of messages, specifically in threads
-    let channel = msg
-        .channel(&ctx)
-        .await
-        .context("Couldn't get channel")?
-        .guild()
-        .context("Couldn't get a guild-channel from the channel")?;
-    if channel.thread_metadata.is_some()
-        || config.category_mod_private == channel.parent_id.context("Couldn't get category_id")?
-        || config.category_modmail == channel.parent_id.context("Couldn't get category_id")?
-    {
-        tracing::Span::current().record("highlights.notified_user_cnt", 0i32);
-        return Ok(0);
-    }
-
-    let mut handled_users = HashSet::new();
-    for (word, users) in highlight_matches {
-        let embed = CreateEmbed::default()
-            .title("Highlight notification")
-            .description(indoc::formatdoc!(
-                "`{word}` has been mentioned in {}
-                [link to message]({})
-
-                Don't care about this anymore?
-                Run `!highlights remove {word}` in #bot to stop getting these notifications.",
-                msg.channel_id.mention(),
-                msg.link(),
-            ))
-            .author_user(&msg.author)
-            .timestamp(msg.timestamp)
-            .footer_str(format!("#{}", channel.name));
-
-        tracing::debug!(
-            highlights.word = %word,
-            highlights.users = ?users,
-            highlights.users_count = %users.len(),
-            "Notifying {} users about a mention of the word '{word}'", users.len()
-        );
-
-        let create_message = embed.into_create_message();
-        for user_id in users {
-            let user_can_see_channel = channel
-                .guild_id
-                .member(&ctx, msg.author.id)
-                .await?
-                .permissions(ctx)?
-                .read_message_history();
-
-            if user_id == msg.author.id
-                // check if the user has already been notified of another word in this message
-                || handled_users.contains(&user_id)
-                // check if the user can read that channel
-                || !user_can_see_channel
-            {
-                continue;
-            }
-            handled_users.insert(user_id);
-
-            let ctx = ctx.clone();
-            let create_message = create_message.clone();
-            tokio::spawn(async move {
-                if let Ok(dm_channel) = user_id.create_dm_channel(&ctx).await {
-                    let _ = dm_channel.send_message(&ctx, create_message).await;
-                }
-            })
-            .instrument(tracing::info_span!("send_highlight_notification"));
-        }
-    }
-
-    tracing::Span::current().record("highlights.notified_user_cnt", handled_users.len());
-    Ok(handled_users.len())
-}
-
 #[tracing::instrument(skip_all, fields(msg_emoji_logging.emoji_used, guild_id = ?msg.guild_id))]
 async fn handle_msg_emoji_logging(ctx: &client::Context, msg: &Message) -> Result<usize> {
     let actual_emojis = util::find_emojis(&msg.content);
@@ -322,70 +264,69 @@ async fn handle_quote(ctx: &client::Context, msg: &Message) -> Result<()> {
     Ok(())
 }
 
+/// Ban non-staff members posting in the honeypot channel, which only spam bots do.
+/// Returns true if the author was banned.
+#[tracing::instrument(skip_all, fields(msg.author = %msg.author.tag(), %msg.id))]
+async fn handle_honeypot(ctx: &client::Context, msg: &Message) -> Result<bool> {
+    let config = ctx.get_config().await;
+    if checks::get_permission_level(&ctx, &msg.author).await? != PermissionLevel::User {
+        return Ok(false);
+    }
+    tracing::info!("Banning user for posting in the honeypot channel");
+    log_error!(msg.delete(&ctx).await);
+
+    let bot_id = ctx.cache.current_user().id;
+    commands::ban::ban_user(
+        ctx,
+        config.guild,
+        bot_id,
+        &msg.author,
+        "[AUTO] Posting in the honeypot channel",
+        1,
+        msg.link(),
+    )
+    .await?;
+    modlog::log_honeypot_ban(ctx, msg).await;
+    Ok(true)
+}
+
 #[tracing::instrument(skip_all)]
 async fn handle_spam_protect(ctx: &client::Context, msg: &Message) -> Result<bool> {
-    let account_age = Utc::now() - *msg.author.created_at();
-
-    if msg.mentions.is_empty() || account_age > chrono::Duration::hours(24) {
+    if msg.guild_id.is_none() {
         return Ok(false);
     }
+    let spam_detector =
+        ctx.data.read().await.get::<SpamDetector>().cloned().context("No spam detector set")?;
+    let Some((spam_kind, spam_msgs)) = spam_detector.check_message(msg) else {
+        return Ok(false);
+    };
+    if checks::get_permission_level(ctx, &msg.author).await? != PermissionLevel::User {
+        return Ok(false);
+    }
+    tracing::info!(spam.kind = ?spam_kind, spam.msg_cnt = spam_msgs.len(), "Detected spam");
+
+    let guild = msg.guild(&ctx.cache).context("Failed to load guild")?.to_owned();
+    let member = guild.member(&ctx, msg.author.id).await?;
+    let bot_id = ctx.cache.current_user().id;
+
+    let duration = std::time::Duration::from_secs(60 * 30);
+
+    commands::mute::apply_mute(
+        &ctx,
+        bot_id,
+        member.into_owned(),
+        duration,
+        Some(format!("[AUTO] {spam_kind}: \"{}\"", msg.content)),
+        msg.link(),
+    )
+    .await?;
+    modlog::log_automute(ctx, msg, duration, &spam_kind.to_string()).await;
 
-    // TODO should the messages be cached here? given the previous checks this is rare enough to probably not matter.
-    let msgs =
-        msg.channel_id.messages(&ctx, GetMessages::default().before(msg.id).limit(10)).await?;
-
-    let spam_msgs =
-        msgs.iter().filter(|x| x.author == msg.author && x.content == msg.content).collect_vec();
-
-    let is_spam = spam_msgs.len() > 3
-        && match spam_msgs.iter().minmax_by_key(|x| *x.timestamp) {
-            itertools::MinMaxResult::NoElements => false,
-            itertools::MinMaxResult::OneElement(_) => false,
-            itertools::MinMaxResult::MinMax(min, max) => {
-                (*max.timestamp - *min.timestamp).num_minutes() < 2
-            }
-        };
-
-    let default_pfp = msg.author.avatar.is_none();
-    let ping_spam_msgs = msgs.iter().filter(|x| x.author == msg.author).collect_vec();
-    let is_ping_spam = default_pfp
-        && ping_spam_msgs.len() > 3
-        && ping_spam_msgs.iter().map(|m| m.mentions.len()).sum::<usize>() as f32
-            >= ping_spam_msgs.len() as f32 * 1.5
-        && match spam_msgs.iter().minmax_by_key(|x| *x.timestamp) {
-            itertools::MinMaxResult::NoElements => false,
-            itertools::MinMaxResult::OneElement(_) => false,
-            itertools::MinMaxResult::MinMax(min, max) => {
-                (*max.timestamp - *min.timestamp).num_minutes() < 2
-            }
-        };
-
-    if is_spam || is_ping_spam {
-        let guild = msg.guild(&ctx.cache).context("Failed to load guild")?.to_owned();
-        let member = guild.member(&ctx, msg.author.id).await?;
-        let bot_id = ctx.cache.current_user().id;
-
-        let duration = std::time::Duration::from_secs(60 * 30);
-
-        commands::mute::apply_mute(
-            &ctx,
-            bot_id,
-            member.into_owned(),
-            duration,
-            Some(format!("[AUTO] spamming \"{}\"", msg.content)),
-            msg.link(),
-        )
-        .await?;
-        modlog::log_mute_for_spamming(ctx, msg, duration).await;
-        log_error!(
-            msg.channel_id
-                .delete_messages(&ctx, msgs.iter().filter(|m| m.author == msg.author))
-                .await
-        );
-        Ok(true)
-    } else {
-        Ok(false)
+    let spam_msgs_by_channel = spam_msgs.into_iter().into_group_map();
+    for (channel_id, msg_ids) in spam_msgs_by_channel {
+        log_error!(channel_id.delete_messages(&ctx, msg_ids).await);
     }
+    Ok(true)
 }
 
 #[tracing::instrument(skip_all)]
@@ -396,12 +337,12 @@ async fn handle_showcase_post(ctx: &client::Context, msg: &Message) -> Result<()
     } else if msg.attachments.is_empty() && msg.embeds.is_empty() && !msg.content.contains("http") {
         tracing::debug!(msg = ?msg, "Deleting invalid showcase post");
         msg.delete(&ctx).await.context("Failed to delete invalid showcase submission")?;
-        msg.author.direct_message(&ctx, CreateMessage::default()
+        ctx.get_dm_queue().await.send(msg.author.id, CreateMessage::default()
                 .content(indoc::indoc!("
                     Your showcase submission was detected to be invalid. If you wanna comment on a rice, create a thread.
                     If this is a mistake, contact the moderators or open an issue on https://github.com/unixporn/robbb
                 "))
-            ).await.context("Failed to send DM about invalid showcase submission")?;
+            );
     } else {
         msg.react(&ctx, ReactionType::Unicode("❤️".to_string()))
             .await
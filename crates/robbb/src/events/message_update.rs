@@ -41,7 +41,13 @@ pub async fn message_update(
     msg.guild_id = event.guild_id;
 
     match handle_blocklist::handle_blocklist(&ctx, &msg).await {
-        Ok(false) => {}
+        Ok(false) => match handle_invite_filter::handle_invite_filter(&ctx, &msg).await {
+            Ok(false) => log_error!(
+                "error while handling link filter in message_update",
+                handle_link_filter::handle_link_filter(&ctx, &msg).await
+            ),
+            err => log_error!("error while handling invite filter in message_update", err),
+        },
         err => log_error!("error while handling blocklist in message_update", err),
     };
 
@@ -17,11 +17,19 @@ use serenity::all::{
 use serenity::client;
 
 mod auto_moderation_action;
+mod component_interaction;
 mod guild_audit_log_entry_create;
 mod guild_member_addition;
 mod guild_member_removal;
 mod guild_member_update;
 mod handle_blocklist;
+mod handle_highlighting;
+mod handle_invite_filter;
+mod handle_link_filter;
+mod handle_phishing;
+mod handle_tag_proposal;
+mod handle_tag_suggestions;
+mod handle_verification;
 mod message_create;
 mod message_delete;
 mod message_update;
@@ -170,6 +178,15 @@ impl client::EventHandler for Handler {
             };
         }
 
+        if let Interaction::Component(interaction) = &interaction {
+            tracing::Span::current()
+                .record("interaction_create.custom_id", interaction.data.custom_id.as_str());
+            match component_interaction::handle_component_interaction(&ctx, interaction).await {
+                Ok(handled) if handled => return,
+                err => log_error!("Error handling component interaction", err),
+            };
+        }
+
         self.dispatch_poise_event(&ctx, FullEvent::InteractionCreate { interaction }).await;
     }
 
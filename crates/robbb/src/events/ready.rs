@@ -27,20 +27,30 @@ pub async fn ready(ctx: client::Context, _data_about_bot: Ready) -> Result<()> {
     dehoist_everyone(ctx.clone(), config.guild).await;
 
     start_mute_handler(ctx.clone()).await;
+    start_verification_handler(ctx.clone()).await;
+    start_highlight_digest_handler(ctx.clone()).await;
     start_attachment_log_handler(ctx).await;
     Ok(())
 }
 
+/// How many members get checked at once when going through everyone on startup,
+/// so the sweep doesn't flood discord with nickname edits.
+const DEHOIST_CONCURRENCY: usize = 8;
+
 #[tracing::instrument(skip_all)]
 async fn dehoist_everyone(ctx: client::Context, guild_id: GuildId) {
     guild_id
         .members_iter(&ctx)
         .filter_map(|x| async { x.ok() })
-        .for_each_concurrent(None, |member| async {
-            log_error!(
-                "Error while dehoisting a member",
-                guild_member_update::dehoist_member(ctx.clone(), member).await
-            );
+        .for_each_concurrent(DEHOIST_CONCURRENCY, |member| async {
+            let nickname_reset =
+                guild_member_update::check_member_name_blocklist(&ctx, &member).await;
+            if !nickname_reset {
+                log_error!(
+                    "Error while dehoisting a member",
+                    guild_member_update::dehoist_member(ctx.clone(), member).await
+                );
+            }
         })
         .await;
 }
@@ -92,6 +102,30 @@ async fn start_mute_handler(ctx: client::Context) {
     });
 }
 
+async fn start_verification_handler(ctx: client::Context) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            log_error!(
+                "Failed to kick unverified members",
+                handle_verification::kick_unverified_members(&ctx).await
+            );
+        }
+    });
+}
+
+async fn start_highlight_digest_handler(ctx: client::Context) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            log_error!(
+                "Failed to send highlight digests",
+                handle_highlighting::send_highlight_digests(&ctx).await
+            );
+        }
+    });
+}
+
 async fn start_attachment_log_handler(ctx: client::Context) {
     let config = ctx.get_config().await;
     tokio::spawn(async move {
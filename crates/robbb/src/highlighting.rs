@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serenity::{
+    model::{
+        id::{ChannelId, UserId},
+        Timestamp,
+    },
+    prelude::TypeMapKey,
+};
+
+/// Users that wrote in a channel within this time don't get highlighted there, if they enabled that.
+const ACTIVE_WINDOW: Duration = Duration::from_secs(60 * 5);
+
+/// Maximum number of highlights kept per digest. Further ones only get counted.
+const MAX_DIGEST_LEN: usize = 20;
+
+/// A highlight notification that was held back to be sent as part of a digest.
+#[derive(Debug, Clone)]
+pub struct PendingHighlight {
+    pub matched: String,
+    pub channel_id: ChannelId,
+    pub author: String,
+    pub link: String,
+    pub timestamp: Timestamp,
+}
+
+#[derive(Debug, Default)]
+struct Digest {
+    pending: Vec<PendingHighlight>,
+    omitted_cnt: usize,
+    last_sent: Option<Instant>,
+}
+
+/// A digest that is ready to be sent.
+#[derive(Debug)]
+pub struct DueDigest {
+    pub user_id: UserId,
+    pub highlights: Vec<PendingHighlight>,
+    /// Highlights that didn't fit into the digest
+    pub omitted_cnt: usize,
+}
+
+/// Runtime state of the highlight notifications, i.e. cooldowns and pending digests.
+/// This is only kept in memory, so pending digests are lost on restart.
+#[derive(Debug, Default)]
+pub struct HighlightState {
+    last_triggered: parking_lot::Mutex<HashMap<(UserId, ChannelId), Instant>>,
+    last_active: parking_lot::Mutex<HashMap<(UserId, ChannelId), Instant>>,
+    digests: parking_lot::Mutex<HashMap<UserId, Digest>>,
+}
+
+impl TypeMapKey for HighlightState {
+    type Value = std::sync::Arc<HighlightState>;
+}
+
+impl HighlightState {
+    pub fn record_activity(&self, user_id: UserId, channel_id: ChannelId) {
+        self.last_active.lock().insert((user_id, channel_id), Instant::now());
+    }
+
+    pub fn was_active_recently(&self, user_id: UserId, channel_id: ChannelId) -> bool {
+        self.last_active
+            .lock()
+            .get(&(user_id, channel_id))
+            .is_some_and(|x| x.elapsed() < ACTIVE_WINDOW)
+    }
+
+    /// Record a highlight of the user in the channel, returning true if the previous one was within the cooldown.
+    /// As every highlight resets the cooldown, users only get notified again once the conversation died down.
+    pub fn check_cooldown(
+        &self,
+        user_id: UserId,
+        channel_id: ChannelId,
+        cooldown: Duration,
+    ) -> bool {
+        let previous = self.last_triggered.lock().insert((user_id, channel_id), Instant::now());
+        previous.is_some_and(|x| x.elapsed() < cooldown)
+    }
+
+    pub fn queue_digest(&self, user_id: UserId, highlight: PendingHighlight) {
+        let mut digests = self.digests.lock();
+        let digest = digests.entry(user_id).or_default();
+        if digest.pending.len() < MAX_DIGEST_LEN {
+            digest.pending.push(highlight);
+        } else {
+            digest.omitted_cnt += 1;
+        }
+    }
+
+    /// Take all digests that should be sent now.
+    /// `get_interval` returns the digest interval of a user, or None if the users digest is not due yet,
+    /// i.e. because of their quiet hours.
+    pub fn take_due_digests(
+        &self,
+        get_interval: impl Fn(UserId) -> Option<Duration>,
+    ) -> Vec<DueDigest> {
+        let mut digests = self.digests.lock();
+        let mut due = Vec::new();
+        for (user_id, digest) in digests.iter_mut().filter(|(_, x)| !x.pending.is_empty()) {
+            let Some(interval) = get_interval(*user_id) else { continue };
+            if digest.last_sent.is_some_and(|x| x.elapsed() < interval) {
+                continue;
+            }
+            digest.last_sent = Some(Instant::now());
+            due.push(DueDigest {
+                user_id: *user_id,
+                highlights: std::mem::take(&mut digest.pending),
+                omitted_cnt: std::mem::take(&mut digest.omitted_cnt),
+            });
+        }
+        due
+    }
+
+    /// Forget about cooldowns and activity older than `max_age`, as well as sent digests.
+    pub fn cleanup(&self, max_age: Duration) {
+        self.last_triggered.lock().retain(|_, x| x.elapsed() < max_age);
+        self.last_active.lock().retain(|_, x| x.elapsed() < ACTIVE_WINDOW);
+        self.digests.lock().retain(|_, x| {
+            !x.pending.is_empty() || x.last_sent.is_some_and(|x| x.elapsed() < max_age)
+        });
+    }
+}
@@ -37,7 +37,7 @@ pub fn init_tracing() {
             && m.name() == "handle_gateway_dispatch"
             && m.fields()
                 .field("event")
-                .map_or(false, |event| event.as_ref().starts_with("PresenceUpdate")))
+                .is_some_and(|event| event.as_ref().starts_with("PresenceUpdate")))
     });
 
     let remove_recv_event_filter = FilterFn::new(|m| {
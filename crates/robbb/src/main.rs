@@ -7,14 +7,19 @@ use pyroscope_pprofrs::{pprof_backend, PprofConfig};
 use robbb_commands::{checks, commands};
 use robbb_db::Db;
 
-use robbb_util::{config::Config, extensions::ChannelIdExt, prelude::Ctx, UserData};
+use robbb_util::{
+    config::Config, dm_queue::DmQueue, extensions::ChannelIdExt, prelude::Ctx, UserData,
+};
 use serenity::all::OnlineStatus;
 use std::sync::Arc;
 
 pub mod attachment_logging;
 mod error_handling;
 pub mod events;
+pub mod highlighting;
 mod logging;
+pub mod spam_protection;
+pub mod tag_suggestions;
 
 use crate::logging::*;
 
@@ -106,6 +111,7 @@ async fn setup_discord_client() -> anyhow::Result<serenity::Client> {
 
     let config = Arc::new(config);
     let db = Arc::new(db);
+    let dm_queue = Arc::new(DmQueue::default());
 
     let event_handler = Arc::new(events::Handler::new(
         framework_options,
@@ -113,6 +119,7 @@ async fn setup_discord_client() -> anyhow::Result<serenity::Client> {
             config: config.clone(),
             db: db.clone(),
             up_emotes: Arc::new(parking_lot::RwLock::new(None)),
+            dm_queue: dm_queue.clone(),
         },
     ));
 
@@ -132,10 +139,21 @@ async fn setup_discord_client() -> anyhow::Result<serenity::Client> {
 
     {
         let mut client_data = client.data.write().await;
+        client_data.insert::<spam_protection::SpamDetector>(Arc::new(
+            spam_protection::SpamDetector::new(config.spam_protection.clone()),
+        ));
+        client_data.insert::<highlighting::HighlightState>(Arc::new(
+            highlighting::HighlightState::default(),
+        ));
+        client_data.insert::<tag_suggestions::TagSuggestionState>(Arc::new(
+            tag_suggestions::TagSuggestionState::default(),
+        ));
+        client_data.insert::<DmQueue>(dm_queue.clone());
         client_data.insert::<Config>(config);
         client_data.insert::<Db>(db);
     }
 
+    dm_queue.start(client.http.clone());
     event_handler.set_shard_manager(client.shard_manager.clone());
     tracing::info!("Initialized client");
     Ok(client)
@@ -0,0 +1,160 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use robbb_util::{config::SpamProtectionConfig, util};
+use serenity::{
+    model::{
+        channel::Message,
+        id::{ChannelId, MessageId, UserId},
+    },
+    prelude::TypeMapKey,
+};
+
+/// The kind of spam a user was caught sending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpamKind {
+    MessageRate,
+    Duplicates,
+    CrossChannelDuplicates,
+    Mentions,
+    Attachments,
+    EmojiWall,
+}
+
+impl std::fmt::Display for SpamKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpamKind::MessageRate => write!(f, "sending too many messages"),
+            SpamKind::Duplicates => write!(f, "repeating the same message"),
+            SpamKind::CrossChannelDuplicates => {
+                write!(f, "posting the same message in many channels")
+            }
+            SpamKind::Mentions => write!(f, "mass mentioning"),
+            SpamKind::Attachments => write!(f, "flooding attachments"),
+            SpamKind::EmojiWall => write!(f, "posting an emoji wall"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RecentMessage {
+    id: MessageId,
+    channel_id: ChannelId,
+    received_at: Instant,
+    /// Normalized content, used to detect duplicates. Empty for messages without text.
+    content: String,
+    mention_cnt: usize,
+    attachment_cnt: usize,
+}
+
+impl RecentMessage {
+    fn from_message(msg: &Message) -> Self {
+        Self {
+            id: msg.id,
+            channel_id: msg.channel_id,
+            received_at: Instant::now(),
+            content: msg.content.trim().to_lowercase(),
+            mention_cnt: msg.mentions.len()
+                + msg.mention_roles.len()
+                + usize::from(msg.mention_everyone),
+            attachment_cnt: msg.attachments.len(),
+        }
+    }
+}
+
+/// Keeps a sliding window of recent messages per user in memory,
+/// so spam can be detected without fetching any message history.
+#[derive(Debug)]
+pub struct SpamDetector {
+    config: SpamProtectionConfig,
+    recent: parking_lot::Mutex<HashMap<UserId, VecDeque<RecentMessage>>>,
+    last_cleanup: parking_lot::Mutex<Instant>,
+}
+
+impl TypeMapKey for SpamDetector {
+    type Value = std::sync::Arc<SpamDetector>;
+}
+
+impl SpamDetector {
+    pub fn new(config: SpamProtectionConfig) -> Self {
+        Self {
+            config,
+            recent: parking_lot::Mutex::new(HashMap::new()),
+            last_cleanup: parking_lot::Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Record a message and check if its author is spamming.
+    /// If so, returns the kind of spam together with all of their messages within the window,
+    /// and forgets about those messages, such that the same messages don't trigger twice.
+    pub fn check_message(&self, msg: &Message) -> Option<(SpamKind, Vec<(ChannelId, MessageId)>)> {
+        let now = Instant::now();
+        self.cleanup_if_due(now);
+
+        let mut recent = self.recent.lock();
+        let user_msgs = recent.entry(msg.author.id).or_default();
+        while user_msgs.front().is_some_and(|x| now - x.received_at > self.config.window) {
+            user_msgs.pop_front();
+        }
+        let new_msg = RecentMessage::from_message(msg);
+        let emoji_cnt = count_emojis(&msg.content);
+        user_msgs.push_back(new_msg);
+
+        let kind = self.detect_spam(user_msgs, emoji_cnt)?;
+        let msgs = user_msgs.drain(..).map(|x| (x.channel_id, x.id)).collect();
+        Some((kind, msgs))
+    }
+
+    fn detect_spam(&self, msgs: &VecDeque<RecentMessage>, emoji_cnt: usize) -> Option<SpamKind> {
+        let config = &self.config;
+        let newest = msgs.back()?;
+
+        if emoji_cnt > config.max_emojis_per_message {
+            return Some(SpamKind::EmojiWall);
+        }
+        if msgs.iter().map(|x| x.mention_cnt).sum::<usize>() > config.max_mentions {
+            return Some(SpamKind::Mentions);
+        }
+        if msgs.iter().map(|x| x.attachment_cnt).sum::<usize>() > config.max_attachments {
+            return Some(SpamKind::Attachments);
+        }
+        if !newest.content.is_empty() {
+            let duplicates =
+                msgs.iter().filter(|x| x.content == newest.content).collect::<Vec<_>>();
+            let channel_cnt = duplicates.iter().map(|x| x.channel_id).collect::<HashSet<_>>().len();
+            if channel_cnt > config.max_duplicate_channels {
+                return Some(SpamKind::CrossChannelDuplicates);
+            }
+            if duplicates.len() > config.max_duplicates {
+                return Some(SpamKind::Duplicates);
+            }
+        }
+        if msgs.len() > config.max_messages {
+            return Some(SpamKind::MessageRate);
+        }
+        None
+    }
+
+    /// Every now and then, forget about users that haven't sent anything within the window.
+    fn cleanup_if_due(&self, now: Instant) {
+        let mut last_cleanup = self.last_cleanup.lock();
+        if now - *last_cleanup < Duration::from_secs(60 * 5) {
+            return;
+        }
+        *last_cleanup = now;
+        self.recent.lock().retain(|_, msgs| {
+            msgs.back().is_some_and(|x| now - x.received_at <= self.config.window)
+        });
+    }
+}
+
+/// Count the custom and unicode emojis in a message.
+/// Unicode emojis are approximated by the most common emoji codepoint ranges.
+fn count_emojis(content: &str) -> usize {
+    let custom_cnt = util::find_emojis(content).len();
+    let unicode_cnt = content
+        .chars()
+        .filter(|c| matches!(*c as u32, 0x1F300..=0x1FAFF | 0x2600..=0x27BF))
+        .count();
+    custom_cnt + unicode_cnt
+}
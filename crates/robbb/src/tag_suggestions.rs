@@ -0,0 +1,44 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serenity::{model::id::ChannelId, prelude::TypeMapKey};
+
+/// After a suggestion, no further tags get suggested in the same channel for this long.
+const CHANNEL_COOLDOWN: Duration = Duration::from_secs(60 * 5);
+
+/// The same tag doesn't get suggested again in the same channel for this long.
+const TAG_COOLDOWN: Duration = Duration::from_secs(60 * 30);
+
+/// Cooldowns of the tag suggestions in tech support, so the bot doesn't keep nagging.
+/// This is only kept in memory, so cooldowns reset on restart.
+#[derive(Debug, Default)]
+pub struct TagSuggestionState {
+    last_suggestion: parking_lot::Mutex<HashMap<ChannelId, Instant>>,
+    last_suggested_tag: parking_lot::Mutex<HashMap<(ChannelId, String), Instant>>,
+}
+
+impl TypeMapKey for TagSuggestionState {
+    type Value = std::sync::Arc<TagSuggestionState>;
+}
+
+impl TagSuggestionState {
+    /// Check whether the tag may be suggested in the channel right now, and if so, start its cooldowns.
+    pub fn try_start_cooldown(&self, channel_id: ChannelId, tag: &str) -> bool {
+        let now = Instant::now();
+        let mut last_suggestion = self.last_suggestion.lock();
+        let mut last_suggested_tag = self.last_suggested_tag.lock();
+        let key = (channel_id, tag.to_lowercase());
+
+        let channel_ready =
+            last_suggestion.get(&channel_id).is_none_or(|x| now - *x >= CHANNEL_COOLDOWN);
+        let tag_ready = last_suggested_tag.get(&key).is_none_or(|x| now - *x >= TAG_COOLDOWN);
+        if !channel_ready || !tag_ready {
+            return false;
+        }
+
+        last_suggestion.insert(channel_id, now);
+        last_suggested_tag.retain(|_, x| now - *x < TAG_COOLDOWN);
+        last_suggested_tag.insert(key, now);
+        true
+    }
+}
@@ -15,16 +15,30 @@ pub async fn gather_attachments(ctx: Ctx<'_>) -> Res<()> {
     ctx.defer().await?;
     let db = ctx.get_db();
 
-    let tag_names = db.list_tags().await?;
-    for tag_name in &tag_names {
+    let tags = db.list_tags().await?;
+    for tag_name in tags.iter().map(|x| &x.name) {
         let Some(tag) = db.get_tag(tag_name).await? else { continue };
 
         let metadata = serde_json::json!({"kind": "tag", "tag_name": tag_name});
-        let result =
-            cdn_hack::persist_cdn_links_in_string(ctx.serenity_context(), &tag.content, metadata)
-                .await;
+        let result = async {
+            let content = cdn_hack::persist_cdn_links_in_string(
+                ctx.serenity_context(),
+                &tag.content,
+                metadata.clone(),
+            )
+            .await?;
+            let embed = match tag.embed.clone() {
+                Some(embed) => Some(
+                    super::tag::persist_tag_embed_images(ctx.serenity_context(), embed, metadata)
+                        .await?,
+                ),
+                None => None,
+            };
+            anyhow::Ok((content, embed))
+        }
+        .await;
 
-        let new_content = match result {
+        let (new_content, new_embed) = match result {
             Ok(x) => x,
             err => {
                 log_error!(err);
@@ -32,13 +46,10 @@ pub async fn gather_attachments(ctx: Ctx<'_>) -> Res<()> {
             }
         };
 
-        if new_content != tag.content {
+        if new_content != tag.content || new_embed != tag.embed {
             db.set_tag(
-                tag.moderator,
-                tag.name.to_string(),
-                new_content,
-                tag.official,
-                tag.create_date,
+                &robbb_db::tag::Tag { content: new_content, embed: new_embed, ..tag },
+                ctx.author().id,
             )
             .await?;
         }
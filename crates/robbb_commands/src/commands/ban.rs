@@ -1,8 +1,9 @@
 use anyhow::Context;
 use chrono::{Duration, Utc};
-use poise::serenity_prelude::{Message, User};
+use poise::serenity_prelude::{GuildId, Message, User};
 use robbb_util::embeds;
 use serenity::builder::{CreateEmbed, EditMessage};
+use serenity::client;
 
 use crate::checks::{self, PermissionLevel};
 
@@ -195,32 +196,56 @@ async fn handle_single_ban(
         return Err(BanFailedReason::HelperRestriction(user));
     }
 
-    let _ = user
-        .dm(
-            &ctx.serenity_context(),
+    ban_user(
+        ctx.serenity_context(),
+        guild.id,
+        ctx.author().id,
+        &user,
+        reason,
+        delete_days,
+        ctx_message.link(),
+    )
+    .await?;
+
+    Ok(user)
+}
+
+/// Ban a user, notifying them about the reason and logging the ban as a note.
+/// Does not check for any permissions.
+pub async fn ban_user(
+    ctx: &client::Context,
+    guild_id: GuildId,
+    moderator: UserId,
+    user: &User,
+    reason: &str,
+    delete_days: u8,
+    context_link: String,
+) -> anyhow::Result<()> {
+    let guild_name = guild_id.name(&ctx.cache).unwrap_or_else(|| "the server".to_string());
+    let _ = ctx
+        .get_dm_queue()
+        .await
+        .deliver(
+            user.id,
             CreateEmbed::default()
-                .title(format!("You were banned from {}", guild.name))
+                .title(format!("You were banned from {guild_name}"))
                 .field("Reason", reason, false)
                 .into_create_message(),
         )
         .await;
 
-    let db = ctx.get_db();
-    guild
-        .ban_with_reason(&ctx.serenity_context(), &user, delete_days, reason)
-        .await
-        .context("Ban failed")?;
+    let db = ctx.get_db().await;
+    guild_id.ban_with_reason(&ctx, user, delete_days, reason).await.context("Ban failed")?;
 
     // Log the ban as a Note in the database
     db.add_mod_action(
-        ctx.author().id,
+        moderator,
         user.id,
         reason.to_string(),
         Utc::now(),
-        ctx_message.link(),
+        context_link,
         robbb_db::mod_action::ModActionKind::Ban,
     )
     .await?;
-
-    Ok(user)
+    Ok(())
 }
@@ -1,8 +1,28 @@
-use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Context;
+use chrono::Utc;
+use poise::CreateReply;
+use regex::{Regex, RegexBuilder};
+use robbb_db::{blocklist::BlocklistStats, mod_action::ModActionKind};
 use robbb_util::embeds;
+use serenity::{
+    all::{ChannelId, ChannelType},
+    builder::{
+        CreateActionRow, CreateButton, CreateInteractionResponse, CreateInteractionResponseMessage,
+        GetMessages,
+    },
+    model::channel::Message,
+};
 
 use super::*;
 
+/// Characters that are removed from text before checking it against the blocklist.
+pub const INVISIBLE_CHARS: &[char] = &['\u{200b}', '\u{200c}', '\u{200d}', '\u{200e}', '\u{200f}'];
+
+/// Upper bound of messages fetched per channel when scanning for a new blocklist entry.
+const MAX_BLOCKLIST_SCAN_MSGS_PER_CHANNEL: usize = 1000;
+
 pub static SHOULD_NEVER_TRIGGER_BLOCKLIST: &[&str] = &[
     "",
     "Hello, I am new to linux, and I'd love to get some help with my GNOME installation.",
@@ -30,6 +50,10 @@ pub async fn blocklist(_ctx: Ctx<'_>) -> Res<()> {
 pub async fn blocklist_add(
     ctx: Ctx<'_>,
     #[description = "Regex pattern for the blocked word"] pattern: String,
+    #[min = 1]
+    #[max = 72]
+    #[description = "Scan the messages of the last N hours for this pattern"]
+    scan_hours: Option<u64>,
 ) -> Res<()> {
     let db = ctx.get_db();
 
@@ -39,13 +63,217 @@ pub async fn blocklist_add(
         abort_with!("Pattern matches one of the test strings it should never match. Make sure you're not matching the empty string or anything else you don't want to.")
     }
 
+    if scan_hours.is_some() {
+        ctx.defer().await?;
+    }
+
     db.add_blocklist_entry(ctx.author().id, &pattern).await?;
 
     ctx.say_success(format!("Added `{}` to the blocklist", pattern)).await?;
 
+    if let Some(scan_hours) = scan_hours {
+        let regex = RegexBuilder::new(&pattern).case_insensitive(true).build()?;
+        let matches = scan_recent_messages(ctx, &regex, scan_hours).await?;
+        offer_blocklist_cleanup(ctx, &pattern, &regex, matches, scan_hours).await?;
+    }
+
+    Ok(())
+}
+
+/// Find all messages of the last `hours` hours in the text channels of the guild that match the given regex.
+/// Messages by bots and moderators are ignored.
+#[tracing::instrument(skip_all, fields(blocklist.scan_hours = %hours))]
+async fn scan_recent_messages(ctx: Ctx<'_>, regex: &Regex, hours: u64) -> Res<Vec<Message>> {
+    let config = ctx.get_config();
+    let cutoff = Utc::now() - chrono::Duration::hours(hours as i64);
+
+    let channels = ctx
+        .guild()
+        .context("Not in a guild")?
+        .channels
+        .values()
+        .filter(|x| x.kind == ChannelType::Text)
+        .map(|x| x.id)
+        .collect_vec();
+
+    let mut matches = Vec::new();
+    for channel in channels {
+        let mut before = None;
+        let mut fetched_cnt = 0;
+        'channel: while fetched_cnt < MAX_BLOCKLIST_SCAN_MSGS_PER_CHANNEL {
+            let mut request = GetMessages::default().limit(100);
+            if let Some(before) = before {
+                request = request.before(before);
+            }
+            // Channels we can't read are simply skipped
+            let Ok(msgs) = channel.messages(&ctx.serenity_context(), request).await else { break };
+            let Some(last) = msgs.last() else { break };
+            before = Some(last.id);
+            fetched_cnt += msgs.len();
+
+            for msg in msgs {
+                if *msg.timestamp < cutoff {
+                    break 'channel;
+                }
+                let normalized = msg.content.replace(INVISIBLE_CHARS, "");
+                if !msg.author.bot && regex.is_match(&normalized) {
+                    matches.push(msg);
+                }
+            }
+        }
+    }
+
+    let mut mods = HashSet::new();
+    for author in matches.iter().map(|x| &x.author).unique_by(|x| x.id).collect_vec() {
+        if author.has_role(&ctx, config.guild, config.role_mod).await? {
+            mods.insert(author.id);
+        }
+    }
+    matches.retain(|x| !mods.contains(&x.author.id));
+    matches.sort_by_key(|x| x.timestamp);
+    Ok(matches)
+}
+
+/// Show the messages found in a scan, and delete them and add notes to their authors once confirmed.
+async fn offer_blocklist_cleanup(
+    ctx: Ctx<'_>,
+    pattern: &str,
+    regex: &Regex,
+    matches: Vec<Message>,
+    scan_hours: u64,
+) -> Res<()> {
+    if matches.is_empty() {
+        ctx.reply_embed_ephemeral_builder(|e| {
+            e.description(format!("No messages in the last {scan_hours} hours match `{pattern}`"))
+        })
+        .await?;
+        return Ok(());
+    }
+
+    const MAX_SHOWN_MATCHES: usize = 15;
+    let mut description = matches
+        .iter()
+        .take(MAX_SHOWN_MATCHES)
+        .map(|msg| {
+            format!(
+                "{} in {}: [jump]({}) `{}`",
+                msg.author.id.mention(),
+                msg.channel_id.mention(),
+                msg.link(),
+                util::ellipsis_text(&msg.content.replace('`', "'"), 60),
+            )
+        })
+        .join("\n");
+    if matches.len() > MAX_SHOWN_MATCHES {
+        description.push_str(&format!("\n...and {} more", matches.len() - MAX_SHOWN_MATCHES));
+    }
+
+    let delete_id = format!("{}-blocklist-scan-delete", ctx.id());
+    let cancel_id = format!("{}-blocklist-scan-cancel", ctx.id());
+    let handle = ctx
+        .send(
+            CreateReply::default()
+                .embed(
+                    embeds::base_embed(&ctx)
+                        .title(format!(
+                            "{} messages of the last {scan_hours} hours match `{pattern}`",
+                            matches.len()
+                        ))
+                        .description(description),
+                )
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new(&delete_id)
+                        .label("Delete all and add notes")
+                        .style(serenity::all::ButtonStyle::Danger),
+                    CreateButton::new(&cancel_id).label("Cancel"),
+                ])]),
+        )
+        .await?;
+
+    let interaction = handle
+        .message()
+        .await?
+        .await_component_interaction(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .custom_ids(vec![delete_id.clone(), cancel_id])
+        .timeout(std::time::Duration::from_secs(120))
+        .await;
+
+    let Some(interaction) = interaction.filter(|x| x.data.custom_id == delete_id) else {
+        handle.edit(ctx, CreateReply::default().components(vec![])).await?;
+        return Ok(());
+    };
+    interaction
+        .create_response(
+            &ctx,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::default().components(vec![]),
+            ),
+        )
+        .await?;
+
+    let deleted_cnt = delete_blocklist_matches(ctx, pattern, regex, &matches).await?;
+
+    ctx.serenity_context()
+        .log_automod_action(|e| {
+            e.author_user(ctx.author())
+                .title("Blocklist cleanup")
+                .description(format!(
+                    "Deleted {deleted_cnt} messages of the last {scan_hours} hours matching `{pattern}`"
+                ))
+        })
+        .await;
+    ctx.say_success(format!("Deleted {deleted_cnt} messages and added notes to their authors"))
+        .await?;
     Ok(())
 }
 
+/// Delete the given messages, adding a blocklist note to each of their authors.
+/// Returns the number of messages that were deleted.
+async fn delete_blocklist_matches(
+    ctx: Ctx<'_>,
+    pattern: &str,
+    regex: &Regex,
+    matches: &[Message],
+) -> Res<usize> {
+    let db = ctx.get_db();
+    let bot_id = ctx.serenity_context().cache.current_user().id;
+
+    let mut by_channel: HashMap<ChannelId, Vec<&Message>> = HashMap::new();
+    for msg in matches {
+        by_channel.entry(msg.channel_id).or_default().push(msg);
+    }
+
+    let mut deleted_cnt = 0;
+    for (channel, msgs) in by_channel {
+        for chunk in msgs.chunks(100) {
+            let result =
+                channel.delete_messages(&ctx.serenity_context(), chunk.iter().map(|x| x.id)).await;
+            if let Err(err) = result {
+                tracing::error!(error.message = %err, channel.id = %channel, "Failed to delete messages");
+                continue;
+            }
+            deleted_cnt += chunk.len();
+
+            for msg in chunk {
+                let normalized = msg.content.replace(INVISIBLE_CHARS, "");
+                let word = regex.find(&normalized).map(|x| x.as_str()).unwrap_or(pattern);
+                db.add_mod_action(
+                    bot_id,
+                    msg.author.id,
+                    format!("Message deleted because of word `{word}` (retroactive scan)"),
+                    Utc::now(),
+                    msg.link(),
+                    ModActionKind::BlocklistViolation,
+                )
+                .await?;
+                db.record_blocklist_hit(pattern, msg.author.id).await?;
+            }
+        }
+    }
+    Ok(deleted_cnt)
+}
+
 /// Remove a pattern from the blocklist
 #[poise::command(
     slash_command,
@@ -67,31 +295,81 @@ pub async fn blocklist_remove(
     Ok(())
 }
 
-/// Get all blocklist entries
+/// Blocklist entries that haven't been hit for this many days are considered stale.
+const STALE_BLOCKLIST_DAYS: i64 = 90;
+
+/// Get all blocklist entries, together with how often they were hit
 #[poise::command(
     slash_command,
     guild_only,
     custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
     rename = "list"
 )]
-pub async fn blocklist_list(ctx: Ctx<'_>) -> Res<()> {
+pub async fn blocklist_list(
+    ctx: Ctx<'_>,
+    #[description = "Only show entries that have not been hit in 90 days"]
+    #[flag]
+    stale: bool,
+) -> Res<()> {
     let config = ctx.get_config();
 
     let db = ctx.get_db();
-    let entries = db.get_blocklist().await?;
+    let mut entries = db.get_blocklist_entries().await?;
+    entries.sort_by_key(|x| std::cmp::Reverse((x.stats.hit_count, x.stats.last_hit)));
 
-    let is_in_mod_bot_stuff = ctx.channel_id() == config.channel_mod_bot_stuff;
-    let embed = embeds::base_embed(&ctx)
-        .title("Blocklist")
-        .description(entries.iter().map(|x| format!("`{x}`")).join("\n"));
-    if is_in_mod_bot_stuff {
-        ctx.reply_embed(embed).await?;
+    let stale_cutoff = Utc::now() - chrono::Duration::days(STALE_BLOCKLIST_DAYS);
+    let title = if stale {
+        entries.retain(|x| {
+            x.stats.last_hit.is_none_or(|last_hit| last_hit < stale_cutoff)
+                && x.create_date.is_none_or(|create_date| create_date < stale_cutoff)
+        });
+        format!("Blocklist entries not hit in {STALE_BLOCKLIST_DAYS} days")
     } else {
-        ctx.reply_embed_ephemeral(embed).await?;
+        "Blocklist".to_string()
+    };
+
+    let mut fields = entries
+        .iter()
+        .map(|x| (format!("`{}`", x.pattern), format_blocklist_stats(&x.stats)))
+        .collect_vec();
+
+    // Automod keywords that aren't part of our blocklist still get recorded, so show them as well.
+    if !stale {
+        let automod_stats = db.get_blocklist_stats().await?;
+        let automod_only = automod_stats
+            .iter()
+            .filter(|stats| !entries.iter().any(|x| x.pattern == stats.pattern))
+            .sorted_by_key(|x| std::cmp::Reverse((x.hit_count, x.last_hit)));
+        fields.extend(automod_only.map(|stats| {
+            (format!("`{}` (automod)", stats.pattern), format_blocklist_stats(stats))
+        }));
     }
+
+    let base_embed = embeds::base_embed(&ctx).description(format!("{} entries", fields.len()));
+
+    let is_in_mod_bot_stuff = ctx.channel_id() == config.channel_mod_bot_stuff;
+    embeds::PaginatedEmbed::create_from_fields(title, fields, base_embed)
+        .await
+        .reply_to(ctx, !is_in_mod_bot_stuff)
+        .await?;
     Ok(())
 }
 
+fn format_blocklist_stats(stats: &BlocklistStats) -> String {
+    match (stats.last_hit, stats.last_offender) {
+        (Some(last_hit), Some(offender)) => format!(
+            "{} hits, last {} by {}",
+            stats.hit_count,
+            util::format_date_ago(last_hit),
+            offender.mention()
+        ),
+        (Some(last_hit), None) => {
+            format!("{} hits, last {}", stats.hit_count, util::format_date_ago(last_hit))
+        }
+        _ => "Never hit".to_string(),
+    }
+}
+
 async fn autocomplete_blocklist_entry(ctx: Ctx<'_>, partial: &str) -> Vec<String> {
     let db = ctx.get_db();
     if let Ok(blocklist) = db.get_blocklist().await {
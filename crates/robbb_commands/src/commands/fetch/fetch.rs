@@ -7,9 +7,20 @@ use serenity::{all::User, builder::CreateEmbedAuthor};
 
 use super::*;
 
+/// Look at a users setup
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "fetch",
+    subcommands("fetch_show", "fetch_history")
+)]
+pub async fn fetch(_ctx: Ctx<'_>) -> Res<()> {
+    Ok(())
+}
+
 /// Fetch a users system information.
-#[poise::command(slash_command, guild_only, rename = "fetch")]
-pub async fn fetch(
+#[poise::command(slash_command, guild_only, rename = "show")]
+pub async fn fetch_show(
     ctx: Ctx<'_>,
     #[description = "The user"] user: Option<User>,
     #[description = "The specific field you care about"] field: Option<FetchField>,
@@ -0,0 +1,112 @@
+use std::str::FromStr;
+
+use robbb_db::{
+    fetch::{diff_fetch_info, Fetch, FetchFieldChange},
+    fetch_field::FetchField,
+};
+use robbb_util::{cdn_hack::FakeCdnId, embeds};
+use serenity::all::User;
+
+use super::*;
+
+/// How many previous setups are shown at most.
+const MAX_FETCH_HISTORY_ENTRIES: i64 = 25;
+
+/// Page through a users previous setups.
+#[poise::command(slash_command, guild_only, rename = "history")]
+pub async fn fetch_history(
+    ctx: Ctx<'_>,
+    #[description = "The user"] user: Option<User>,
+) -> Res<()> {
+    let db = ctx.get_db();
+    let user = user.unwrap_or_else(|| ctx.author().clone());
+    ctx.defer().await?;
+
+    let current = db.get_fetch(user.id).await?;
+    let history = db.get_fetch_history(user.id, MAX_FETCH_HISTORY_ENTRIES).await?;
+    if history.is_empty() && current.as_ref().is_none_or(|x| x.info.is_empty()) {
+        abort_with!(UserErr::new(format!("{} has never set their fetch", user.name)));
+    }
+    let has_current = current.is_some();
+    let snapshots = current.into_iter().chain(history).collect_vec();
+
+    let mut pages = Vec::new();
+    for (idx, snapshot) in snapshots.iter().enumerate() {
+        let mut embed = embeds::base_embed(&ctx)
+            .author_user(&user)
+            .title(format!("{}'s setups ({}/{})", user.name, idx + 1, snapshots.len()))
+            .timestamp_opt(snapshot.create_date);
+
+        let state = match (idx, has_current) {
+            (0, true) if snapshot.info.is_empty() => "No fetch set at the moment",
+            (0, true) => "Current setup",
+            _ => "Previous setup",
+        };
+        let date = snapshot.create_date.map(util::format_date_ago).unwrap_or_default();
+        embed = embed.description(format!("{state}, set {date}"));
+
+        for (key, value) in snapshot.clone().get_values_ordered() {
+            if key == FetchField::Image {
+                // Old images may have been deleted in the meantime, which shouldn't hide the rest of the history
+                if let Ok(link) = resolve_fetch_image(&ctx, &value).await {
+                    embed = embed.image(link);
+                }
+            } else {
+                embed =
+                    embed.field_opt(key.to_string(), format_fetch_field_value(&key, value), true);
+            }
+        }
+
+        if let Some(previous) = snapshots.get(idx + 1) {
+            embed = embed.field(
+                "Changes since the previous setup",
+                format_fetch_changes(previous, snapshot),
+                false,
+            );
+        }
+        pages.push(embed);
+    }
+
+    embeds::PaginatedEmbed::create(pages, embeds::base_embed(&ctx))
+        .await
+        .reply_to(ctx, false)
+        .await?;
+    Ok(())
+}
+
+async fn resolve_fetch_image(ctx: &Ctx<'_>, value: &str) -> anyhow::Result<String> {
+    FakeCdnId::from_str(value)?.resolve(ctx).await
+}
+
+/// Describe what changed between two setups, one line per field.
+fn format_fetch_changes(old: &Fetch, new: &Fetch) -> String {
+    let format_value = |field: &FetchField, value: &str| {
+        let value = format_fetch_field_value(field, value.to_string()).unwrap_or_default();
+        util::ellipsis_text(&value, 100)
+    };
+    let changes = diff_fetch_info(&old.info, &new.info)
+        .into_iter()
+        .map(|change| match change {
+            FetchFieldChange::Added(FetchField::Image, _) => "+ Image".to_string(),
+            FetchFieldChange::Removed(FetchField::Image, _) => "- Image".to_string(),
+            FetchFieldChange::Changed(FetchField::Image, _, _) => "~ Image".to_string(),
+            FetchFieldChange::Added(field, value) => {
+                format!("+ **{}**: {}", field, format_value(&field, &value))
+            }
+            FetchFieldChange::Removed(field, value) => {
+                format!("- **{}**: ~~{}~~", field, format_value(&field, &value))
+            }
+            FetchFieldChange::Changed(field, old, new) => format!(
+                "~ **{}**: {} → {}",
+                field,
+                format_value(&field, &old),
+                format_value(&field, &new)
+            ),
+        })
+        .join("\n");
+    if changes.is_empty() {
+        "Nothing".to_string()
+    } else {
+        util::ellipsis_text(&changes, 1024)
+    }
+}
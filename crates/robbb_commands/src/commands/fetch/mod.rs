@@ -4,6 +4,8 @@ pub mod fetch;
 use robbb_db::fetch_field::FetchField;
 
 pub use fetch::*;
+pub mod fetchhistory;
+pub use fetchhistory::*;
 pub mod setfetch;
 pub use setfetch::*;
 
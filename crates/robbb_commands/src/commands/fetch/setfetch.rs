@@ -4,7 +4,6 @@ use robbb_util::cdn_hack;
 use std::str::FromStr;
 
 use super::*;
-use std::collections::HashMap;
 
 const SETFETCH_USAGE: &str = indoc::indoc!("
     Run this:
@@ -110,7 +109,7 @@ pub async fn set_fetch_update(
     Ok(())
 }
 
-/// Clear your fetch data
+/// Clear your fetch data (clearing everything also removes your fetch history)
 #[poise::command(slash_command, guild_only, rename = "clear")]
 pub async fn set_fetch_clear(
     ctx: Ctx<'_>,
@@ -126,8 +125,8 @@ pub async fn set_fetch_clear(
         }
         ctx.say_success(format!("Successfully cleared your {}", field)).await?;
     } else {
-        db.set_fetch(ctx.author().id, HashMap::new(), Some(Utc::now())).await?;
-        ctx.say_success("Successfully cleared your fetch data!").await?;
+        db.clear_fetch(ctx.author().id).await?;
+        ctx.say_success("Successfully cleared your fetch data and its history!").await?;
     }
     Ok(())
 }
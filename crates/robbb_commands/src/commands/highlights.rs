@@ -1,4 +1,9 @@
-use poise::serenity_prelude::CreateEmbed;
+use poise::serenity_prelude::{CreateEmbed, GuildChannel, User};
+use robbb_db::{
+    highlight_settings::HighlightSettings,
+    highlights::{HighlightMode, HighlightTrigger},
+};
+use robbb_util::embeds;
 
 use super::*;
 use crate::checks::{self, PermissionLevel};
@@ -8,7 +13,14 @@ use crate::checks::{self, PermissionLevel};
     slash_command,
     rename = "highlight",
     aliases("highlights", "hl"),
-    subcommands("highlights_add", "highlights_list", "highlights_clear", "highlights_remove",)
+    subcommands(
+        "highlights_add",
+        "highlights_list",
+        "highlights_clear",
+        "highlights_remove",
+        "highlights_ignore",
+        "highlights_settings",
+    )
 )]
 pub async fn highlights(_: Ctx<'_>) -> Res<()> {
     Ok(())
@@ -18,11 +30,16 @@ pub async fn highlights(_: Ctx<'_>) -> Res<()> {
 #[poise::command(slash_command, guild_only, rename = "add")]
 pub async fn highlights_add(
     ctx: Ctx<'_>,
-    #[description = "The word you want to be notified about"] trigger: String,
+    #[description = "The word (or regex) you want to be notified about"] trigger: String,
+    #[description = "How to match the word (default: whole word)"] mode: Option<HighlightMode>,
 ) -> Res<()> {
     if trigger.len() < 3 {
         abort_with!("Highlight has to be longer than 2 characters");
     }
+    let trigger = HighlightTrigger::new(&trigger, mode.unwrap_or(HighlightMode::Word));
+    if let Err(err) = trigger.validate() {
+        abort_with!(UserErr::new(format!("That's not a valid highlight: {err:#}")));
+    }
 
     let db = ctx.get_db();
     let max_highlight_cnt =
@@ -41,13 +58,11 @@ pub async fn highlights_add(
         )));
     }
 
-    ctx.author()
-        .id
-        .create_dm_channel(&ctx.serenity_context())
-        .await
-        .user_error("Couldn't open a DM to you - do you have me blocked?")?
-        .send_message(
-            &ctx.serenity_context(),
+    let dm_queue = ctx.get_dm_queue();
+    dm_queue.forget_closed_dms(ctx.author().id);
+    dm_queue
+        .deliver(
+            ctx.author().id,
             CreateEmbed::default()
                 .title("Test to see if you can receive DMs")
                 .description(format!(
@@ -73,16 +88,35 @@ pub async fn highlights_list(ctx: Ctx<'_>) -> Res<()> {
     let db = ctx.get_db();
     let highlights = db.get_highlights().await?;
 
-    let highlights_list = highlights.triggers_for_user(ctx.author().id).join("\n");
+    let highlights_list =
+        highlights.triggers_for_user(ctx.author().id).map(|x| format!("`{x}`")).join("\n");
 
     if highlights_list.is_empty() {
         abort_with!("You don't seem to have set any highlights");
-    } else {
-        ctx.reply_embed_ephemeral_builder(|e| {
-            e.title("Your highlights").description(highlights_list)
-        })
-        .await?;
     }
+
+    let ignores = db.get_highlight_ignores().await?;
+    let ignored_channels = ignores
+        .ignored_channels
+        .get(&ctx.author().id)
+        .map(|x| x.iter().map(|x| x.mention().to_string()).join(", "))
+        .unwrap_or_default();
+    let blocked_users = ignores
+        .blocked_users
+        .get(&ctx.author().id)
+        .map(|x| x.iter().map(|x| x.mention().to_string()).join(", "))
+        .unwrap_or_default();
+    ctx.reply_embed_ephemeral_builder(|mut e| {
+        e = e.title("Your highlights").description(highlights_list);
+        if !ignored_channels.is_empty() {
+            e = e.field("Ignored channels", ignored_channels, false);
+        }
+        if !blocked_users.is_empty() {
+            e = e.field("Ignored users", blocked_users, false);
+        }
+        e
+    })
+    .await?;
     Ok(())
 }
 
@@ -95,7 +129,11 @@ pub async fn highlights_remove(
     trigger: String,
 ) -> Res<()> {
     let db = ctx.get_db();
-    db.remove_highlight(ctx.author().id, trigger.clone())
+    let highlights = db.get_highlights().await?;
+    let highlight = highlights
+        .find_trigger_of_user(ctx.author().id, &trigger)
+        .user_error("You don't have a highlight for that")?;
+    db.remove_highlight(ctx.author().id, highlight)
         .await
         .user_error("Failed to remove the highlight.")?;
     ctx.say_success(format!("You will no longer be notified when someone says '{}'", trigger))
@@ -103,6 +141,135 @@ pub async fn highlights_remove(
     Ok(())
 }
 
+/// Stop getting highlighted in a channel or by a user
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "ignore",
+    subcommands("highlights_ignore_channel", "highlights_ignore_user")
+)]
+pub async fn highlights_ignore(_: Ctx<'_>) -> Res<()> {
+    Ok(())
+}
+
+/// Ignore (or stop ignoring) a channel or category for your highlights
+#[poise::command(slash_command, guild_only, rename = "channel")]
+pub async fn highlights_ignore_channel(
+    ctx: Ctx<'_>,
+    #[description = "The channel or category to ignore"] channel: GuildChannel,
+) -> Res<()> {
+    let db = ctx.get_db();
+    let ignored = db.toggle_highlight_ignored_channel(ctx.author().id, channel.id).await?;
+    if ignored {
+        ctx.say_success(format!("You will no longer get highlighted in {}", channel.mention()))
+            .await?;
+    } else {
+        ctx.say_success(format!("You will get highlighted in {} again", channel.mention())).await?;
+    }
+    Ok(())
+}
+
+/// Ignore (or stop ignoring) messages by a user for your highlights
+#[poise::command(slash_command, guild_only, rename = "user")]
+pub async fn highlights_ignore_user(
+    ctx: Ctx<'_>,
+    #[description = "The user whose messages should not highlight you"] user: User,
+) -> Res<()> {
+    if user.id == ctx.author().id {
+        abort_with!("Your own messages never highlight you");
+    }
+    let db = ctx.get_db();
+    let blocked = db.toggle_highlight_blocked_user(ctx.author().id, user.id).await?;
+    if blocked {
+        ctx.say_success(format!("Messages by {} will no longer highlight you", user.mention()))
+            .await?;
+    } else {
+        ctx.say_success(format!("Messages by {} will highlight you again", user.mention())).await?;
+    }
+    Ok(())
+}
+
+/// Change how you get notified about your highlights
+#[poise::command(slash_command, guild_only, rename = "settings")]
+pub async fn highlights_settings(
+    ctx: Ctx<'_>,
+    #[description = "Minutes before you get notified again about highlights in the same channel"]
+    #[min = 0]
+    #[max = 120]
+    cooldown: Option<i64>,
+    #[description = "Don't notify you in channels you recently wrote in"]
+    suppress_when_active: Option<bool>,
+    #[description = "Hours in which to hold back notifications, i.e. `22-7`, or `off`"]
+    quiet_hours: Option<String>,
+    #[description = "Your timezone as offset from UTC in hours, used for the quiet hours"]
+    #[min = -12]
+    #[max = 14]
+    utc_offset: Option<i64>,
+    #[description = "Batch notifications into one DM every this many minutes, 0 to disable"]
+    #[min = 0]
+    #[max = 1440]
+    digest: Option<i64>,
+) -> Res<()> {
+    let db = ctx.get_db();
+    let mut settings = db.get_highlight_settings_of(ctx.author().id).await?;
+    let old_settings = settings.clone();
+
+    if let Some(cooldown) = cooldown {
+        settings.cooldown_minutes = cooldown;
+    }
+    if let Some(suppress_when_active) = suppress_when_active {
+        settings.suppress_when_active = suppress_when_active;
+    }
+    if let Some(quiet_hours) = quiet_hours {
+        settings.quiet_hours = parse_quiet_hours(&quiet_hours)
+            .user_error("Invalid quiet hours, use something like `22-7` or `off`")?;
+    }
+    if let Some(utc_offset) = utc_offset {
+        settings.utc_offset_hours = utc_offset;
+    }
+    if let Some(digest) = digest {
+        settings.digest_minutes = Some(digest).filter(|x| *x > 0);
+    }
+    if settings != old_settings {
+        db.set_highlight_settings(ctx.author().id, &settings).await?;
+    }
+
+    ctx.reply_embed_ephemeral(format_highlight_settings(&ctx, &settings)).await?;
+    Ok(())
+}
+
+/// Parse quiet hours like `22-7`. Returns `Some(None)` for `off`.
+fn parse_quiet_hours(value: &str) -> Option<Option<(u32, u32)>> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("off") {
+        return Some(None);
+    }
+    let (start, end) = value.split_once('-')?;
+    let start: u32 = start.trim().parse().ok().filter(|x| *x < 24)?;
+    let end: u32 = end.trim().parse().ok().filter(|x| *x < 24)?;
+    Some(Some((start, end)).filter(|_| start != end))
+}
+
+fn format_highlight_settings(ctx: &Ctx<'_>, settings: &HighlightSettings) -> CreateEmbed {
+    let quiet_hours = match settings.quiet_hours {
+        Some((start, end)) => {
+            format!("{start}:00 - {end}:00 (UTC{:+})", settings.utc_offset_hours)
+        }
+        None => "Off".to_string(),
+    };
+    let digest = match settings.digest_minutes {
+        Some(minutes) => format!("Every {minutes} minutes"),
+        None => "Off".to_string(),
+    };
+    let suppress_when_active = if settings.suppress_when_active { "Yes" } else { "No" };
+    embeds::base_embed(ctx)
+        .title("Your highlight settings")
+        .field("Cooldown per channel", format!("{} minutes", settings.cooldown_minutes), true)
+        .field("Skip channels you're active in", suppress_when_active, true)
+        .field("Quiet hours", quiet_hours, true)
+        .field("Digest", digest, true)
+}
+
 /// Remove all of your highlights
 #[poise::command(slash_command, guild_only, rename = "clear")]
 pub async fn highlights_clear(ctx: Ctx<'_>) -> Res<()> {
@@ -117,8 +284,8 @@ async fn autocomplete_highlights(ctx: Ctx<'_>, partial: &str) -> Vec<String> {
     if let Ok(highlights) = db.get_highlights().await {
         highlights
             .triggers_for_user(ctx.author().id)
-            .filter(|x| x.contains(partial))
-            .map(|x| x.to_string())
+            .filter(|x| x.word.contains(partial))
+            .map(|x| x.word.to_string())
             .collect_vec()
     } else {
         Vec::new()
@@ -0,0 +1,111 @@
+use poise::serenity_prelude::{AutocompleteChoice, GuildId};
+use robbb_util::embeds;
+
+use super::*;
+
+/// Control which servers may be advertised with invite links
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "invitefilter",
+    subcommands("invite_filter_allow", "invite_filter_remove", "invite_filter_list",)
+)]
+pub async fn invite_filter(_ctx: Ctx<'_>) -> Res<()> {
+    Ok(())
+}
+
+/// Allow invites to the server of the given invite
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "allow"
+)]
+pub async fn invite_filter_allow(
+    ctx: Ctx<'_>,
+    #[description = "An invite to the server, i.e. `discord.gg/code`"] invite: String,
+) -> Res<()> {
+    let db = ctx.get_db();
+
+    let codes = util::find_invite_codes(&invite);
+    let code = codes.first().map(|x| x.as_str()).unwrap_or(invite.trim());
+    let invite = ctx
+        .http()
+        .get_invite(code, false, false, None)
+        .await
+        .user_error("Couldn't resolve that invite. Is it still valid?")?;
+    let guild = invite.guild.user_error("That invite doesn't lead to a server")?;
+
+    db.add_invite_allowlist_entry(ctx.author().id, guild.id, &guild.name).await?;
+    ctx.say_success(format!("Invites to `{}` are now allowed", guild.name)).await?;
+    Ok(())
+}
+
+/// Remove a server from the invite allowlist
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "remove"
+)]
+pub async fn invite_filter_remove(
+    ctx: Ctx<'_>,
+    #[autocomplete = "autocomplete_invite_allowlist_entry"]
+    #[description = "Server to remove from the invite allowlist"]
+    server: String,
+) -> Res<()> {
+    let db = ctx.get_db();
+    let guild_id = server.parse().map(GuildId::new).user_error("Unknown server")?;
+    if !db.remove_invite_allowlist_entry(guild_id).await? {
+        abort_with!("That server is not on the invite allowlist");
+    }
+    ctx.say_success("Removed the server from the invite allowlist").await?;
+    Ok(())
+}
+
+/// List all servers that may be advertised
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "list"
+)]
+pub async fn invite_filter_list(ctx: Ctx<'_>) -> Res<()> {
+    let config = ctx.get_config();
+    let db = ctx.get_db();
+    let entries = db.get_invite_allowlist().await?;
+
+    let embed = embeds::base_embed(&ctx).title("Invite allowlist").description(
+        entries
+            .iter()
+            .map(|x| {
+                format!("`{}` ({}) - added by {}", x.guild_name, x.guild_id, x.added_by.mention())
+            })
+            .join("\n"),
+    );
+    let is_in_mod_bot_stuff = ctx.channel_id() == config.channel_mod_bot_stuff;
+    if is_in_mod_bot_stuff {
+        ctx.reply_embed(embed).await?;
+    } else {
+        ctx.reply_embed_ephemeral(embed).await?;
+    }
+    Ok(())
+}
+
+async fn autocomplete_invite_allowlist_entry(
+    ctx: Ctx<'_>,
+    partial: &str,
+) -> Vec<AutocompleteChoice> {
+    let db = ctx.get_db();
+    let partial = partial.to_lowercase();
+    if let Ok(entries) = db.get_invite_allowlist().await {
+        entries
+            .into_iter()
+            .filter(|x| x.guild_name.to_lowercase().contains(&partial))
+            .map(|x| AutocompleteChoice::new(x.guild_name, x.guild_id.to_string()))
+            .collect_vec()
+    } else {
+        Vec::new()
+    }
+}
@@ -47,9 +47,11 @@ pub async fn kick(
 }
 
 pub async fn do_kick(ctx: &client::Context, guild: GuildId, user: &User, reason: &str) -> Res<()> {
-    let _ = user
-        .dm(
-            &ctx,
+    let _ = ctx
+        .get_dm_queue()
+        .await
+        .deliver(
+            user.id,
             CreateEmbed::default()
                 .title("You were kicked")
                 .field("Reason", reason, false)
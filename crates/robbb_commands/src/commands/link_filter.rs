@@ -0,0 +1,112 @@
+use robbb_db::domain_filter::{normalize_domain_pattern, DomainFilterKind};
+use robbb_util::embeds;
+
+use super::*;
+
+/// Control which domains may be linked
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "linkfilter",
+    subcommands("link_filter_add", "link_filter_remove", "link_filter_list",)
+)]
+pub async fn link_filter(_ctx: Ctx<'_>) -> Res<()> {
+    Ok(())
+}
+
+/// Deny or allow a domain. Use `*.example.com` to include all subdomains.
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "add"
+)]
+pub async fn link_filter_add(
+    ctx: Ctx<'_>,
+    #[description = "Domain, i.e. `example.com` or `*.example.com`"] domain: String,
+    #[description = "Whether links to this domain should be denied or allowed"]
+    kind: DomainFilterKind,
+) -> Res<()> {
+    let db = ctx.get_db();
+    let domain = normalize_domain_pattern(&domain)
+        .user_error("Invalid domain. Use something like `example.com` or `*.example.com`")?;
+
+    db.set_domain_filter_entry(ctx.author().id, &domain, kind).await?;
+    let verb = match kind {
+        DomainFilterKind::Deny => "denied",
+        DomainFilterKind::Allow => "allowed",
+    };
+    ctx.say_success(format!("Links to `{domain}` are now {verb}")).await?;
+    Ok(())
+}
+
+/// Remove a domain from the link filter
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "remove"
+)]
+pub async fn link_filter_remove(
+    ctx: Ctx<'_>,
+    #[autocomplete = "autocomplete_link_filter_entry"]
+    #[description = "Domain to remove from the link filter"]
+    domain: String,
+) -> Res<()> {
+    let db = ctx.get_db();
+    if !db.remove_domain_filter_entry(&domain).await? {
+        abort_with!(UserErr::new(format!("`{domain}` is not in the link filter")));
+    }
+    ctx.say_success(format!("Removed `{domain}` from the link filter")).await?;
+    Ok(())
+}
+
+/// List all domains in the link filter
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "list"
+)]
+pub async fn link_filter_list(ctx: Ctx<'_>) -> Res<()> {
+    let config = ctx.get_config();
+    let db = ctx.get_db();
+    let filter = db.get_domain_filter().await?;
+
+    let format_kind = |kind: DomainFilterKind| {
+        filter
+            .entries
+            .iter()
+            .filter(|x| x.kind == kind)
+            .map(|x| format!("`{}`", x.domain))
+            .join("\n")
+    };
+    let or_none = |s: String| if s.is_empty() { "None".to_string() } else { s };
+    let mut embed = embeds::base_embed(&ctx)
+        .title("Link filter")
+        .field("Denied", or_none(format_kind(DomainFilterKind::Deny)), false)
+        .field("Allowed", or_none(format_kind(DomainFilterKind::Allow)), false);
+    if let Some(hours) = config.link_filter_new_member_hours {
+        embed = embed.description(format!(
+            "New members may only post links to allowed domains for {hours} hours after joining."
+        ));
+    }
+
+    let is_in_mod_bot_stuff = ctx.channel_id() == config.channel_mod_bot_stuff;
+    if is_in_mod_bot_stuff {
+        ctx.reply_embed(embed).await?;
+    } else {
+        ctx.reply_embed_ephemeral(embed).await?;
+    }
+    Ok(())
+}
+
+async fn autocomplete_link_filter_entry(ctx: Ctx<'_>, partial: &str) -> Vec<String> {
+    let db = ctx.get_db();
+    if let Ok(filter) = db.get_domain_filter().await {
+        filter.entries.into_iter().map(|x| x.domain).filter(|x| x.contains(partial)).collect_vec()
+    } else {
+        Vec::new()
+    }
+}
@@ -20,12 +20,15 @@ pub mod fetch;
 pub mod help;
 pub mod highlights;
 pub mod info;
+pub mod invite_filter;
 pub mod kick;
+pub mod link_filter;
 pub mod modping;
 pub mod move_users;
 pub mod mute;
 pub mod note;
 pub mod pfp;
+pub mod phishing;
 pub mod poll;
 pub mod purge;
 pub mod role;
@@ -33,6 +36,7 @@ pub mod small;
 pub mod tag;
 pub mod top;
 pub mod unban;
+pub mod verification;
 pub mod warn;
 
 pub fn all_commands() -> Vec<poise::Command<UserData, Error>> {
@@ -45,6 +49,8 @@ pub fn all_commands() -> Vec<poise::Command<UserData, Error>> {
         poll::poll(),
         tag::tag(),
         tag::taglist(),
+        tag::proposetag(),
+        tag::tagsearch(),
         modping::modping(),
         highlights::highlights(),
         small::latency(),
@@ -64,6 +70,7 @@ pub fn all_commands() -> Vec<poise::Command<UserData, Error>> {
         tag::settag(),
         small::restart(),
         small::say(),
+        small::dm_stats(),
         warn::warn(),
         ban::ban(),
         kick::kick(),
@@ -71,6 +78,10 @@ pub fn all_commands() -> Vec<poise::Command<UserData, Error>> {
         unban::unban(),
         emojistats::emojistats(),
         blocklist::blocklist(),
+        link_filter::link_filter(),
+        invite_filter::invite_filter(),
+        phishing::phishing(),
+        verification::verification(),
         note::note(),
         mute::mute(),
         purge::purge(),
@@ -0,0 +1,151 @@
+use poise::serenity_prelude::Attachment;
+use robbb_db::domain_filter::normalize_domain_pattern;
+use robbb_util::embeds;
+
+use super::*;
+
+/// Maximum size of an imported phishing domain list.
+const MAX_IMPORT_BYTES: u32 = 8 * 1024 * 1024;
+
+/// Manage the list of known phishing domains
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    subcommands("phishing_add", "phishing_remove", "phishing_import", "phishing_list",)
+)]
+pub async fn phishing(_ctx: Ctx<'_>) -> Res<()> {
+    Ok(())
+}
+
+/// Add a domain to the phishing domain list. Includes all of its subdomains.
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "add"
+)]
+pub async fn phishing_add(
+    ctx: Ctx<'_>,
+    #[description = "Domain, i.e. `dlscord-nitro.gift`"] domain: String,
+) -> Res<()> {
+    let db = ctx.get_db();
+    let domain = parse_phishing_domain(&domain)
+        .user_error("Invalid domain. Use something like `example.com`")?;
+    if db.add_phishing_domains(ctx.author().id, std::slice::from_ref(&domain)).await? == 0 {
+        abort_with!(UserErr::new(format!("`{domain}` is already on the phishing domain list")));
+    }
+    ctx.say_success(format!("Added `{domain}` to the phishing domain list")).await?;
+    Ok(())
+}
+
+/// Remove a domain from the phishing domain list
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "remove"
+)]
+pub async fn phishing_remove(
+    ctx: Ctx<'_>,
+    #[description = "Domain to remove from the phishing domain list"] domain: String,
+) -> Res<()> {
+    let db = ctx.get_db();
+    let domain = domain.trim().to_lowercase();
+    if !db.remove_phishing_domain(&domain).await? {
+        abort_with!(UserErr::new(format!("`{domain}` is not on the phishing domain list")));
+    }
+    ctx.say_success(format!("Removed `{domain}` from the phishing domain list")).await?;
+    Ok(())
+}
+
+/// Import phishing domains from a text file, with one domain per line or in hosts-file format
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "import"
+)]
+pub async fn phishing_import(
+    ctx: Ctx<'_>,
+    #[description = "Text file with one domain per line"] file: Attachment,
+) -> Res<()> {
+    let db = ctx.get_db();
+    if file.size > MAX_IMPORT_BYTES {
+        abort_with!("That file is too large");
+    }
+    ctx.defer().await?;
+    let content = file.download().await.user_error("Failed to download the file")?;
+    let content = String::from_utf8(content).user_error("That file is not a text file")?;
+
+    let (domains, invalid): (Vec<_>, Vec<_>) = content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        // hosts-file format, i.e. `0.0.0.0 example.com`
+        .filter_map(|line| line.split_whitespace().last())
+        .map(|x| parse_phishing_domain(x).ok_or(x))
+        .partition_result();
+    let domains = domains.into_iter().unique().collect_vec();
+    if domains.is_empty() {
+        abort_with!("That file doesn't contain any valid domains");
+    }
+
+    let added_cnt = db.add_phishing_domains(ctx.author().id, &domains).await?;
+    let mut response = format!(
+        "Added {added_cnt} new domains to the phishing domain list ({} were already on it)",
+        domains.len() as u64 - added_cnt
+    );
+    if !invalid.is_empty() {
+        response.push_str(&format!(
+            "\nSkipped {} invalid lines, i.e. `{}`",
+            invalid.len(),
+            invalid.iter().take(3).join("`, `")
+        ));
+    }
+    ctx.say_success(response).await?;
+    Ok(())
+}
+
+/// Show the phishing domain list
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "list"
+)]
+pub async fn phishing_list(
+    ctx: Ctx<'_>,
+    #[description = "Only show domains containing this"] filter: Option<String>,
+) -> Res<()> {
+    let config = ctx.get_config();
+    let db = ctx.get_db();
+    let domains = db.get_phishing_domains().await?;
+    let filter = filter.unwrap_or_default().to_lowercase();
+    let matching = domains.iter().filter(|x| x.contains(&filter)).sorted().collect_vec();
+
+    let mut description = matching.iter().take(50).map(|x| format!("`{x}`")).join("\n");
+    if matching.len() > 50 {
+        description.push_str(&format!("\n...and {} more", matching.len() - 50));
+    }
+    if description.is_empty() {
+        description = "No matching domains".to_string();
+    }
+    let embed = embeds::base_embed(&ctx)
+        .title(format!("Phishing domains ({} total)", domains.len()))
+        .description(description);
+
+    let is_in_mod_bot_stuff = ctx.channel_id() == config.channel_mod_bot_stuff;
+    if is_in_mod_bot_stuff {
+        ctx.reply_embed(embed).await?;
+    } else {
+        ctx.reply_embed_ephemeral(embed).await?;
+    }
+    Ok(())
+}
+
+/// Parse a domain for the phishing domain list. Wildcards are not supported,
+/// as subdomains of listed domains are always included.
+fn parse_phishing_domain(domain: &str) -> Option<String> {
+    normalize_domain_pattern(domain).filter(|x| !x.starts_with("*."))
+}
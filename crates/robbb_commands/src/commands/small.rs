@@ -59,6 +59,29 @@ pub async fn latency(ctx: Ctx<'_>) -> Res<()> {
     Ok(())
 }
 
+/// Show statistics about the DMs sent since the bot started
+#[poise::command(
+    slash_command,
+    rename = "dmstats",
+    category = "Bot-Administration",
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }"
+)]
+pub async fn dm_stats(ctx: Ctx<'_>) -> Res<()> {
+    let stats = ctx.get_dm_queue().stats();
+    ctx.reply_embed_builder(|e| {
+        e.title("DM statistics")
+            .field("Delivered", stats.delivered.to_string(), true)
+            .field("Failed", stats.failed.to_string(), true)
+            .field("DMs closed", stats.closed.to_string(), true)
+            .field("Dropped (queue full)", stats.dropped.to_string(), true)
+            .field("Retries", stats.retries.to_string(), true)
+            .field("Currently queued", stats.queued.to_string(), true)
+            .field("Users with closed DMs", stats.known_closed_dms.to_string(), true)
+    })
+    .await?;
+    Ok(())
+}
+
 /// I'm tired,... >.<
 #[poise::command(slash_command, category = "Bot-Administration")]
 pub async fn uptime(ctx: Ctx<'_>) -> Res<()> {
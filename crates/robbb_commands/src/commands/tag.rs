@@ -1,7 +1,18 @@
 use anyhow::Context;
 use chrono::Utc;
+use poise::serenity_prelude::{
+    Attachment, ButtonStyle, CreateActionRow, CreateAllowedMentions, CreateAttachment,
+    CreateButton, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+    CreateMessage, User,
+};
+use poise::CreateReply;
 use poise::Modal;
-use robbb_util::cdn_hack;
+use robbb_db::{
+    tag::{Tag, TagEmbed, TagUsageStats},
+    tag_proposal::TagProposal,
+    tag_trigger::compile_tag_trigger,
+};
+use robbb_util::{cdn_hack, embeds, log_error, tag_archive, tag_template};
 use tracing_futures::Instrument;
 
 use super::*;
@@ -14,46 +25,244 @@ pub async fn tag(
     #[autocomplete = "tag_autocomplete_existing"]
     #[rename = "tag"]
     tag_name: String,
+    #[description = "The user to address the tag to"] user: Option<User>,
+    #[description = "Arguments for the tag, separated by spaces"] args: Option<String>,
 ) -> Res<()> {
     let db = ctx.get_db();
 
     let tag = db.get_tag(&tag_name).await?.user_error("No tag with this name exists")?;
 
-    let moderator = tag.moderator.to_user(&ctx.serenity_context()).await?;
+    let template_ctx = tag_template::TagTemplateContext {
+        user: user.as_ref().map(|x| x.id),
+        author: ctx.author().id,
+        channel: ctx.channel_id(),
+        args: args.unwrap_or_default().split_whitespace().map(|x| x.to_string()).collect(),
+    };
+    let message =
+        render_tag(ctx.serenity_context(), embeds::base_embed(&ctx), &tag, &template_ctx).await?;
 
+    // Only ever ping the user the tag is addressed to
+    let allowed_mentions =
+        CreateAllowedMentions::new().users(user.as_ref().map(|x| x.id)).replied_user(false);
+    let reply =
+        CreateReply { content: message.content, embeds: message.embeds, ..Default::default() };
+    ctx.send(reply.allowed_mentions(allowed_mentions).reply(true)).await?;
+
+    // Only count tags that were actually shown
+    log_error!(db.record_tag_usage(&tag.name, ctx.author().id, ctx.channel_id(), Utc::now()).await);
+    Ok(())
+}
+
+/// A tag rendered into a message.
+#[derive(Debug, Clone)]
+pub struct TagMessage {
+    pub content: Option<String>,
+    pub embeds: Vec<CreateEmbed>,
+}
+
+/// Render a tag into a message, filling in its placeholders and resolving fake-CDN links.
+/// If the tag is addressed to a user, the message mentions them.
+pub async fn render_tag(
+    ctx: &poise::serenity_prelude::Context,
+    base_embed: CreateEmbed,
+    tag: &Tag,
+    template_ctx: &tag_template::TagTemplateContext,
+) -> Res<TagMessage> {
+    let moderator = tag.moderator.to_user(ctx).await?;
+    let content = cdn_hack::resolve_cdn_links_in_string(ctx, &tag.content).await?;
     let content =
-        cdn_hack::resolve_cdn_links_in_string(ctx.serenity_context(), &tag.content).await?;
-    if util::validate_url(&content) {
-        ctx.say(content).await?;
+        tag_template::render(&content, template_ctx).with_user_error(|e| e.to_string())?;
+    let mention = template_ctx.user.map(|x| x.mention().to_string());
+
+    if let Some(tag_embed) = &tag.embed {
+        let embeds =
+            build_rich_tag_embeds(ctx, base_embed, tag_embed, &content, template_ctx).await?;
+        let embeds = embeds
+            .into_iter()
+            .map(|e| e.footer_str(tag_footer(tag, &moderator)).timestamp_opt(tag.create_date))
+            .collect_vec();
+        Ok(TagMessage { content: mention, embeds })
+    } else if util::validate_url(&content) {
+        let content = match mention {
+            Some(mention) => format!("{}\n{}", mention, content),
+            None => content,
+        };
+        Ok(TagMessage { content: Some(content), embeds: Vec::new() })
     } else {
-        ctx.reply_embed_builder(|e| {
-            e.title(&tag.name)
-                .description(content)
-                .footer_str(format!("Written by {}", moderator.tag()))
-                .timestamp_opt(tag.create_date)
-        })
-        .await?;
+        let embed = base_embed
+            .title(&tag.name)
+            .description(content)
+            .footer_str(tag_footer(tag, &moderator))
+            .timestamp_opt(tag.create_date);
+        Ok(TagMessage { content: mention, embeds: vec![embed] })
     }
+}
 
+fn tag_footer(tag: &Tag, author: &User) -> String {
+    if tag.official {
+        format!("Written by {}", author.tag())
+    } else {
+        format!("Community tag proposed by {}", author.tag())
+    }
+}
+
+/// Build the embeds of a rich embed tag, filling in its placeholders and resolving fake-CDN links.
+/// Multiple images are sent as multiple embeds with the same URL, which discord shows as a gallery.
+async fn build_rich_tag_embeds(
+    ctx: &poise::serenity_prelude::Context,
+    base_embed: CreateEmbed,
+    tag_embed: &TagEmbed,
+    description: &str,
+    template_ctx: &tag_template::TagTemplateContext,
+) -> Res<Vec<CreateEmbed>> {
+    let render = |text: &str| tag_template::render(text, template_ctx);
+
+    let mut images = Vec::new();
+    for image in &tag_embed.images {
+        images.push(cdn_hack::resolve_cdn_links_in_string(ctx, image).await?);
+    }
+    let thumbnail = match &tag_embed.thumbnail {
+        Some(thumbnail) => Some(cdn_hack::resolve_cdn_links_in_string(ctx, thumbnail).await?),
+        None => None,
+    };
+
+    let mut embed = base_embed;
+    if let Some(title) = &tag_embed.title {
+        embed = embed.title(render(title).with_user_error(|e| e.to_string())?);
+    }
+    if !description.is_empty() {
+        embed = embed.description(description);
+    }
+    for field in &tag_embed.fields {
+        let value = render(&field.value).with_user_error(|e| e.to_string())?;
+        embed = embed.field(&field.name, value, field.inline);
+    }
+    if let Some(color) = tag_embed.color.as_deref().and_then(parse_embed_color) {
+        embed = embed.color(color);
+    }
+    if let Some(thumbnail) = thumbnail {
+        embed = embed.thumbnail(thumbnail);
+    }
+
+    let Some((first_image, other_images)) = images.split_first() else { return Ok(vec![embed]) };
+    let mut embeds = vec![embed.url(first_image).image(first_image)];
+    embeds.extend(
+        other_images.iter().map(|image| CreateEmbed::default().url(first_image).image(image)),
+    );
+    Ok(embeds)
+}
+
+/// Parse a color given as `#rrggbb`.
+fn parse_embed_color(color: &str) -> Option<u32> {
+    let hex = color.trim().strip_prefix('#').unwrap_or(color.trim());
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok()
+}
+
+/// Search for tags by their name and content
+#[poise::command(slash_command, guild_only, rename = "tagsearch")]
+pub async fn tagsearch(
+    ctx: Ctx<'_>,
+    #[description = "Words to search for"] query: String,
+) -> Res<()> {
+    let db = ctx.get_db();
+
+    let results = db.search_tags(&query, 10).await?;
+    if results.is_empty() {
+        abort_with!("No tags matched your search");
+    }
+    let fields = results.into_iter().map(|x| {
+        let snippet = x.snippet.replace("```", "`\u{200b}``");
+        (x.name, util::ellipsis_text(&snippet, 1000))
+    });
+    ctx.reply_embed_builder(|e| {
+        e.title(format!("Tags matching {}", util::ellipsis_text(&query, 100)))
+            .fields(fields.map(|(name, value)| (name, value, false)))
+    })
+    .await?;
     Ok(())
 }
 
-/// Get the names of all tags
+/// Get the names of all tags, grouped by category
 #[poise::command(slash_command, guild_only, rename = "taglist")]
 pub async fn taglist(ctx: Ctx<'_>) -> Res<()> {
     let db = ctx.get_db();
 
     let tags = db.list_tags().await?;
-    ctx.reply_embed_builder(|e| e.title("Tags").description(tags.join(", "))).await?;
+    let by_category = tags
+        .into_iter()
+        .map(|tag| (tag.category.clone().unwrap_or_else(|| "Uncategorized".to_string()), tag))
+        .into_group_map();
+
+    let fields = by_category
+        .into_iter()
+        .sorted_by(|(a, _), (b, _)| a.to_lowercase().cmp(&b.to_lowercase()))
+        .flat_map(|(category, tags)| {
+            let entries = tags
+                .into_iter()
+                .sorted_by_key(|tag| tag.name.to_lowercase())
+                .map(|tag| {
+                    if tag.aliases.is_empty() {
+                        tag.name
+                    } else {
+                        format!("{} ({})", tag.name, tag.aliases.join(", "))
+                    }
+                })
+                .collect_vec();
+            chunk_list(entries, 1000).into_iter().map(move |chunk| (category.clone(), chunk))
+        })
+        .collect_vec();
+
+    embeds::PaginatedEmbed::create_from_fields(
+        "Tags".to_string(),
+        fields,
+        embeds::base_embed(&ctx),
+    )
+    .await
+    .reply_to(ctx, false)
+    .await?;
     Ok(())
 }
 
+/// Join the given entries with commas, splitting them into chunks no longer than `max_len`.
+fn chunk_list(entries: Vec<String>, max_len: usize) -> Vec<String> {
+    let mut chunks = vec![String::new()];
+    for entry in entries {
+        let current = chunks.last_mut().unwrap();
+        if !current.is_empty() && current.len() + entry.len() + 2 > max_len {
+            chunks.push(entry);
+        } else {
+            if !current.is_empty() {
+                current.push_str(", ");
+            }
+            current.push_str(&entry);
+        }
+    }
+    chunks
+}
+
 /// Manage tags
 #[poise::command(
     slash_command,
     guild_only,
     custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
-    subcommands("tag_set", "tag_delete")
+    subcommands(
+        "tag_set",
+        "tag_set_embed",
+        "tag_delete",
+        "tag_alias",
+        "tag_unalias",
+        "tag_trigger",
+        "tag_untrigger",
+        "tag_triggers",
+        "tag_history",
+        "tag_rollback",
+        "tag_stats",
+        "tag_export",
+        "tag_import"
+    )
 )]
 pub async fn settag(_ctx: Ctx<'_>) -> Res<()> {
     Ok(())
@@ -74,16 +283,469 @@ pub async fn tag_delete(
     tag_name: String,
 ) -> Res<()> {
     let db = ctx.get_db();
-    db.delete_tag(tag_name).await?;
+    let tag = db.get_tag(&tag_name).await?.user_error("No tag with this name exists")?;
+    db.delete_tag(tag.name).await?;
+    ctx.say_success("Succesfully removed!").await?;
+    Ok(())
+}
+
+/// Add an alternative name for a tag
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "alias"
+)]
+pub async fn tag_alias(
+    ctx: Ctx<'_>,
+    #[rename = "tag"]
+    #[description = "Name of the tag"]
+    #[autocomplete = "tag_autocomplete_existing"]
+    tag_name: String,
+    #[description = "The alternative name"] alias: String,
+) -> Res<()> {
+    let db = ctx.get_db();
+    let tag = db.get_tag(&tag_name).await?.user_error("No tag with this name exists")?;
+    if db.get_tag(&alias).await?.is_some() {
+        abort_with!("A tag or alias with that name already exists");
+    }
+    db.add_tag_alias(&tag.name, &alias).await?;
+    ctx.say_success(format!("`{}` is now an alias for `{}`", alias, tag.name)).await?;
+    Ok(())
+}
+
+/// Remove an alternative name of a tag
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "unalias"
+)]
+pub async fn tag_unalias(
+    ctx: Ctx<'_>,
+    #[description = "The alias to remove"]
+    #[autocomplete = "tag_alias_autocomplete"]
+    alias: String,
+) -> Res<()> {
+    let db = ctx.get_db();
+    if !db.remove_tag_alias(&alias).await? {
+        abort_with!("No alias with this name exists");
+    }
+    ctx.say_success("Succesfully removed!").await?;
+    Ok(())
+}
+
+/// Suggest a tag in tech support whenever a message matches a pattern
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "trigger"
+)]
+pub async fn tag_trigger(
+    ctx: Ctx<'_>,
+    #[rename = "tag"]
+    #[description = "Name of the tag"]
+    #[autocomplete = "tag_autocomplete_existing"]
+    tag_name: String,
+    #[description = "Case-insensitive regex that messages must match"] pattern: String,
+) -> Res<()> {
+    let db = ctx.get_db();
+    let tag = db.get_tag(&tag_name).await?.user_error("No tag with this name exists")?;
+    compile_tag_trigger(&pattern).with_user_error(|e| format!("Invalid pattern: {e}"))?;
+    let triggers = db.get_tag_triggers().await?;
+    if triggers.iter().any(|x| x.tag.eq_ignore_ascii_case(&tag.name) && x.pattern == pattern) {
+        abort_with!("This tag already has that trigger");
+    }
+    db.add_tag_trigger(&tag.name, &pattern, ctx.author().id).await?;
+    ctx.say_success(format!(
+        "`{}` will now be suggested for messages matching `{}`",
+        tag.name, pattern
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Stop suggesting a tag for a pattern
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "untrigger"
+)]
+pub async fn tag_untrigger(
+    ctx: Ctx<'_>,
+    #[rename = "tag"]
+    #[description = "Name of the tag"]
+    #[autocomplete = "tag_autocomplete_existing"]
+    tag_name: String,
+    #[description = "The pattern to remove"]
+    #[autocomplete = "tag_trigger_autocomplete"]
+    pattern: String,
+) -> Res<()> {
+    let db = ctx.get_db();
+    let tag = db.get_tag(&tag_name).await?.user_error("No tag with this name exists")?;
+    if !db.remove_tag_trigger(&tag.name, &pattern).await? {
+        abort_with!("This tag has no such trigger");
+    }
     ctx.say_success("Succesfully removed!").await?;
     Ok(())
 }
 
+/// List the patterns that make the bot suggest tags in tech support
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "triggers"
+)]
+pub async fn tag_triggers(ctx: Ctx<'_>) -> Res<()> {
+    let db = ctx.get_db();
+    let config = ctx.get_config();
+
+    let fields = db
+        .get_tag_triggers()
+        .await?
+        .into_iter()
+        .map(|x| (x.tag, x.pattern))
+        .into_group_map()
+        .into_iter()
+        .sorted_by_key(|(tag, _)| tag.to_lowercase())
+        .map(|(tag, patterns)| {
+            let patterns = patterns.iter().map(|x| format!("`{}`", x.replace('`', "'"))).join("\n");
+            (tag, util::ellipsis_text(&patterns, 1000))
+        })
+        .collect_vec();
+
+    let is_in_mod_bot_stuff = ctx.channel_id() == config.channel_mod_bot_stuff;
+    embeds::PaginatedEmbed::create_from_fields(
+        "Tag triggers".to_string(),
+        fields,
+        embeds::base_embed(&ctx),
+    )
+    .await
+    .reply_to(ctx, !is_in_mod_bot_stuff)
+    .await?;
+    Ok(())
+}
+
+/// Show the previous versions of a tag
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "history"
+)]
+pub async fn tag_history(
+    ctx: Ctx<'_>,
+    #[rename = "name"]
+    #[description = "Name of the tag"]
+    #[autocomplete = "tag_autocomplete_existing"]
+    tag_name: String,
+) -> Res<()> {
+    let db = ctx.get_db();
+    let config = ctx.get_config();
+
+    // Resolve aliases, but still allow looking at the history of deleted tags
+    let tag_name = db.get_tag(&tag_name).await?.map(|x| x.name).unwrap_or(tag_name);
+    let revisions = db.get_tag_revisions(&tag_name).await?;
+    if revisions.is_empty() {
+        abort_with!("No tag with this name has ever existed");
+    }
+
+    let latest = revisions.len();
+    let fields = revisions
+        .iter()
+        .rev()
+        .map(|revision| {
+            let previous = revision.number.checked_sub(2).and_then(|idx| revisions.get(idx));
+            let title = if revision.number == latest {
+                format!("Revision {} (latest)", revision.number)
+            } else {
+                format!("Revision {}", revision.number)
+            };
+            let date = revision.create_date.map(util::format_date_ago).unwrap_or_default();
+            let mut diff = match previous {
+                Some(previous) => format_line_diff(&previous.content, &revision.content),
+                None => format_line_diff("", &revision.content),
+            };
+            if previous.map_or(revision.embed.is_some(), |x| x.embed != revision.embed) {
+                diff.push_str("\n*Embed structure changed*");
+            }
+            (title, format!("by {} {}\n{}", revision.moderator.mention(), date, diff))
+        })
+        .collect_vec();
+
+    let is_in_mod_bot_stuff = ctx.channel_id() == config.channel_mod_bot_stuff;
+    embeds::PaginatedEmbed::create_from_fields(
+        format!("History of tag {}", tag_name),
+        fields,
+        embeds::base_embed(&ctx),
+    )
+    .await
+    .reply_to(ctx, !is_in_mod_bot_stuff)
+    .await?;
+    Ok(())
+}
+
+/// Restore a tag to a previous revision
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "rollback"
+)]
+pub async fn tag_rollback(
+    ctx: Ctx<'_>,
+    #[rename = "name"]
+    #[description = "Name of the tag"]
+    #[autocomplete = "tag_autocomplete_existing"]
+    tag_name: String,
+    #[description = "The revision to restore, as shown in /settag history"]
+    #[min = 1]
+    revision: usize,
+) -> Res<()> {
+    let db = ctx.get_db();
+
+    let existing_tag = db.get_tag(&tag_name).await?;
+    let tag_name = existing_tag.as_ref().map(|x| x.name.clone()).unwrap_or(tag_name);
+    let revisions = db.get_tag_revisions(&tag_name).await?;
+    let target = revisions
+        .into_iter()
+        .find(|x| x.number == revision)
+        .user_error("No revision with this number exists for this tag")?;
+
+    if existing_tag.as_ref().is_some_and(|x| x.content == target.content && x.embed == target.embed)
+    {
+        abort_with!("The tag already has the content of this revision");
+    }
+
+    let official = existing_tag.as_ref().is_none_or(|x| x.official);
+    db.set_tag(
+        &Tag {
+            name: tag_name,
+            moderator: tag_credit(existing_tag.as_ref(), official, ctx.author().id),
+            content: target.content,
+            official,
+            create_date: Some(Utc::now()),
+            category: existing_tag.and_then(|x| x.category),
+            embed: target.embed,
+        },
+        ctx.author().id,
+    )
+    .await?;
+    ctx.say_success(format!("Succesfully restored revision {}!", revision)).await?;
+    Ok(())
+}
+
+/// Show which tags are used the most, and which ones haven't been used in a while
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "stats"
+)]
+pub async fn tag_stats(
+    ctx: Ctx<'_>,
+    #[description = "How many days back to count uses for (default: 30)"]
+    #[min = 1]
+    days: Option<u32>,
+) -> Res<()> {
+    let db = ctx.get_db();
+    let config = ctx.get_config();
+
+    let days = days.unwrap_or(30);
+    let since = Utc::now() - chrono::Duration::days(days as i64);
+    let stats = db.get_tag_usage_stats(since).await?;
+
+    let format_uses = |x: &TagUsageStats| {
+        format!("`{}` - {} uses ({} total)", x.name, x.recent_uses, x.total_uses)
+    };
+    let (used, unused): (Vec<_>, Vec<_>) = stats.into_iter().partition(|x| x.recent_uses > 0);
+
+    let most_used = used
+        .iter()
+        .sorted_by_key(|x| std::cmp::Reverse((x.recent_uses, x.total_uses)))
+        .take(10)
+        .map(format_uses)
+        .join("\n");
+    let least_used = used
+        .iter()
+        .sorted_by_key(|x| (x.recent_uses, x.total_uses))
+        .take(10)
+        .map(format_uses)
+        .join("\n");
+    let unused = unused
+        .into_iter()
+        .sorted_by_key(|x| x.last_used)
+        .map(|x| match x.last_used {
+            Some(last_used) => format!("`{}` ({})", x.name, util::format_date_ago(last_used)),
+            None => format!("`{}` (never)", x.name),
+        })
+        .collect_vec();
+
+    let mut fields = vec![
+        (format!("Most used in the last {} days", days), most_used),
+        (format!("Least used in the last {} days", days), least_used),
+    ];
+    fields.extend(
+        chunk_list(unused, 1000)
+            .into_iter()
+            .map(|chunk| (format!("Not used in the last {} days", days), chunk)),
+    );
+    let fields = fields
+        .into_iter()
+        .map(|(title, value)| (title, if value.is_empty() { "None".to_string() } else { value }));
+
+    let is_in_mod_bot_stuff = ctx.channel_id() == config.channel_mod_bot_stuff;
+    embeds::PaginatedEmbed::create_from_fields(
+        "Tag usage".to_string(),
+        fields,
+        embeds::base_embed(&ctx),
+    )
+    .await
+    .reply_to(ctx, !is_in_mod_bot_stuff)
+    .await?;
+    Ok(())
+}
+
+/// Largest tag archive that will be imported.
+const MAX_TAG_ARCHIVE_BYTES: u32 = 8 * 1024 * 1024;
+
+/// Download all tags as a zip archive of markdown files
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "export"
+)]
+pub async fn tag_export(ctx: Ctx<'_>) -> Res<()> {
+    let db = ctx.get_db();
+    ctx.defer_ephemeral().await?;
+
+    let tags = tag_archive::load_tag_files(&db).await?;
+    let archive = tag_archive::write_archive(&tags)?;
+    let file_name = format!("tags-{}.zip", Utc::now().format("%Y-%m-%d"));
+    ctx.send(
+        CreateReply::default()
+            .content(format!("Exported {} tags", tags.len()))
+            .attachment(CreateAttachment::bytes(archive, file_name))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Replace all tags with the contents of an archive created by /settag export
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "import"
+)]
+pub async fn tag_import(
+    ctx: Ctx<'_>,
+    #[description = "Zip archive of tag files"] file: Attachment,
+) -> Res<()> {
+    let db = ctx.get_db();
+    if file.size > MAX_TAG_ARCHIVE_BYTES {
+        abort_with!("That file is too large");
+    }
+    ctx.defer().await?;
+    let content = file.download().await.user_error("Failed to download the file")?;
+    let incoming = tag_archive::read_archive(&content).with_user_error(|e| format!("{e:#}"))?;
+    for tag in &incoming {
+        tag_template::validate(&tag.content)
+            .with_user_error(|e| format!("Invalid tag {}: {}", tag.name, e))?;
+    }
+
+    let current = tag_archive::load_tag_files(&db).await?;
+    let diff = tag_archive::TagArchiveDiff::new(&current, &incoming);
+    if diff.is_empty() {
+        abort_with!("The archive matches the current tags, nothing to import");
+    }
+
+    let apply_id = format!("{}-tag-import-apply", ctx.id());
+    let cancel_id = format!("{}-tag-import-cancel", ctx.id());
+    let handle = ctx
+        .send(
+            CreateReply::default()
+                .embed(
+                    embeds::base_embed(&ctx)
+                        .title("Import tags?")
+                        .description(util::ellipsis_text(&diff.to_string(), 4000)),
+                )
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new(&apply_id).label("Apply").style(ButtonStyle::Danger),
+                    CreateButton::new(&cancel_id).label("Cancel"),
+                ])]),
+        )
+        .await?;
+
+    let interaction = handle
+        .message()
+        .await?
+        .await_component_interaction(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .custom_ids(vec![apply_id.clone(), cancel_id])
+        .timeout(std::time::Duration::from_secs(120))
+        .await;
+
+    let Some(interaction) = interaction.filter(|x| x.data.custom_id == apply_id) else {
+        handle.edit(ctx, CreateReply::default().components(vec![])).await?;
+        return Ok(());
+    };
+    interaction
+        .create_response(
+            &ctx,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::default().components(vec![]),
+            ),
+        )
+        .await?;
+
+    tag_archive::apply_import(&db, &incoming, &diff).await?;
+    ctx.serenity_context()
+        .log_automod_action(|e| {
+            e.author_user(ctx.author())
+                .title("Tags imported")
+                .description(util::ellipsis_text(&diff.to_string(), 4000))
+        })
+        .await;
+    ctx.say_success(format!(
+        "Imported tags: {} added, {} changed, {} removed",
+        diff.added.len(),
+        diff.changed.len(),
+        diff.removed.len()
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Render a line diff between two versions of a tag as a diff code block that fits into an embed field.
+fn format_line_diff(old: &str, new: &str) -> String {
+    let diff = similar::TextDiff::from_lines(old, new);
+    let diff = diff.unified_diff().context_radius(1).to_string();
+    let diff = if diff.trim().is_empty() { "(no changes)".to_string() } else { diff };
+    // Prevent the content from closing the code block early
+    let diff = diff.replace("```", "`\u{200b}``");
+    format!("```diff\n{}\n```", util::ellipsis_text(diff.trim_end(), 900))
+}
+
+/// Who a tag is credited to after a moderator saves it.
+/// Community tags keep crediting their proposer, official tags credit the moderator that last set them.
+fn tag_credit(existing_tag: Option<&Tag>, official: bool, editor: UserId) -> UserId {
+    match existing_tag {
+        Some(tag) if !official => tag.moderator,
+        _ => editor,
+    }
+}
+
 #[derive(Debug, poise::Modal)]
 #[name = "Tag"]
 struct TagModal {
     #[name = "Content"]
-    #[placeholder = "Content of your tag"]
+    #[placeholder = "Content of your tag. Can use {user}, {author}, {channel} and {arg1} to {arg9}"]
     #[paragraph]
     content: String,
 }
@@ -101,18 +763,27 @@ pub async fn tag_set(
     #[description = "The name of the tag"]
     #[autocomplete = "tag_autocomplete"]
     tag_name: String,
+    #[description = "The category to list the tag under"]
+    #[autocomplete = "tag_category_autocomplete"]
+    category: Option<String>,
+    #[description = "Whether this is an official tag (default: unchanged, new tags are official)"]
+    official: Option<bool>,
 ) -> Res<()> {
     let ctx = Ctx::Application(app_ctx);
     let db = ctx.get_db();
 
     let existing_tag = db.get_tag(&tag_name).await?;
+    // When editing a tag through one of its aliases, edit the tag itself
+    let tag_name = existing_tag.as_ref().map(|x| x.name.clone()).unwrap_or(tag_name);
+    let category = category.or_else(|| existing_tag.as_ref().and_then(|x| x.category.clone()));
     // Content to pre-fill into the modal text field
-    let default_content = existing_tag.map(|x| x.content).unwrap_or_default();
+    let default_content = existing_tag.as_ref().map(|x| x.content.clone()).unwrap_or_default();
 
     let result = TagModal::execute_with_defaults(app_ctx, TagModal { content: default_content })
         .instrument(tracing::info_span!("wait for modal response"))
         .await?
         .context("Modal timed out")?;
+    tag_template::validate(&result.content).with_user_error(|e| e.to_string())?;
 
     let content = cdn_hack::persist_cdn_links_in_string(
         ctx.serenity_context(),
@@ -120,11 +791,314 @@ pub async fn tag_set(
         serde_json::json!({"kind": "tag", "tag_name": tag_name}),
     )
     .await?;
-    db.set_tag(ctx.author().id, tag_name, content, true, Some(Utc::now())).await?;
+    let official = official.unwrap_or(existing_tag.as_ref().is_none_or(|x| x.official));
+    db.set_tag(
+        &Tag {
+            name: tag_name,
+            moderator: tag_credit(existing_tag.as_ref(), official, ctx.author().id),
+            content,
+            official,
+            create_date: Some(Utc::now()),
+            category,
+            // Editing the text of a rich embed tag keeps the rest of the embed
+            embed: existing_tag.and_then(|x| x.embed),
+        },
+        ctx.author().id,
+    )
+    .await?;
     ctx.say_success("Succesfully set!").await?;
     Ok(())
 }
 
+/// The fields that may be used in the JSON of a rich embed tag.
+const TAG_EMBED_JSON_KEYS: &[&str] =
+    &["title", "description", "fields", "color", "thumbnail", "images"];
+/// Discord shows at most 4 images in a gallery.
+const MAX_TAG_EMBED_IMAGES: usize = 4;
+/// Discord limits the text of an embed to 6000 characters in total.
+/// This leaves room for the footer and for placeholders that get longer once filled in.
+const MAX_TAG_EMBED_TOTAL_LEN: usize = 5000;
+
+/// JSON representation of a rich embed tag, as edited in /settag embed.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct TagEmbedJson {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    description: String,
+    #[serde(flatten)]
+    embed: TagEmbed,
+}
+
+#[derive(Debug, poise::Modal)]
+#[name = "Rich embed tag"]
+struct TagEmbedModal {
+    #[name = "Embed JSON"]
+    #[placeholder = r#"{"title": "", "description": "", "fields": [], "color": "", "images": []}"#]
+    #[paragraph]
+    json: String,
+}
+
+/// Save a tag as a rich embed, with a title, fields, color and images.
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "embed"
+)]
+pub async fn tag_set_embed(
+    app_ctx: AppCtx<'_>,
+    #[rename = "name"]
+    #[description = "The name of the tag"]
+    #[autocomplete = "tag_autocomplete"]
+    tag_name: String,
+    #[description = "The category to list the tag under"]
+    #[autocomplete = "tag_category_autocomplete"]
+    category: Option<String>,
+    #[description = "Whether this is an official tag (default: unchanged, new tags are official)"]
+    official: Option<bool>,
+) -> Res<()> {
+    let ctx = Ctx::Application(app_ctx);
+    let db = ctx.get_db();
+
+    let existing_tag = db.get_tag(&tag_name).await?;
+    let tag_name = existing_tag.as_ref().map(|x| x.name.clone()).unwrap_or(tag_name);
+    let category = category.or_else(|| existing_tag.as_ref().and_then(|x| x.category.clone()));
+    let default_json = match &existing_tag {
+        Some(tag) => serde_json::to_string_pretty(&TagEmbedJson {
+            description: tag.content.clone(),
+            embed: tag.embed.clone().unwrap_or_default(),
+        })?,
+        None => String::new(),
+    };
+
+    let result =
+        TagEmbedModal::execute_with_defaults(app_ctx, TagEmbedModal { json: default_json })
+            .instrument(tracing::info_span!("wait for modal response"))
+            .await?
+            .context("Modal timed out")?;
+
+    let TagEmbedJson { description, embed } = parse_tag_embed_json(&result.json)?;
+    let metadata = serde_json::json!({"kind": "tag", "tag_name": tag_name});
+    let description = cdn_hack::persist_cdn_links_in_string(
+        ctx.serenity_context(),
+        &description,
+        metadata.clone(),
+    )
+    .await?;
+    let embed = persist_tag_embed_images(ctx.serenity_context(), embed, metadata).await?;
+
+    let official = official.unwrap_or(existing_tag.as_ref().is_none_or(|x| x.official));
+    db.set_tag(
+        &Tag {
+            name: tag_name,
+            moderator: tag_credit(existing_tag.as_ref(), official, ctx.author().id),
+            content: description,
+            official,
+            create_date: Some(Utc::now()),
+            category,
+            embed: Some(embed),
+        },
+        ctx.author().id,
+    )
+    .await?;
+    ctx.say_success("Succesfully set!").await?;
+    Ok(())
+}
+
+/// Parse and validate the JSON of a rich embed tag.
+fn parse_tag_embed_json(json: &str) -> Result<TagEmbedJson, UserErr> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).with_user_error(|e| format!("Invalid JSON: {e}"))?;
+    let object = value.as_object().user_error("The embed must be a JSON object")?;
+    if let Some(key) = object.keys().find(|key| !TAG_EMBED_JSON_KEYS.contains(&key.as_str())) {
+        return Err(UserErr::new(format!(
+            "Unknown field `{key}`, allowed are {}",
+            TAG_EMBED_JSON_KEYS.join(", ")
+        )));
+    }
+    let parsed: TagEmbedJson =
+        serde_json::from_value(value).with_user_error(|e| format!("Invalid embed: {e}"))?;
+    let TagEmbedJson { description, embed } = &parsed;
+
+    let check = |ok: bool, msg: &str| if ok { Ok(()) } else { Err(UserErr::new(msg)) };
+    check(
+        embed.title.is_some() || !description.is_empty() || !embed.fields.is_empty(),
+        "The embed needs a title, description or fields",
+    )?;
+    check(embed.title.as_ref().is_none_or(|x| x.len() <= 256), "The title is too long")?;
+    check(description.len() <= 4096, "The description is too long")?;
+    check(embed.fields.len() <= 25, "Embeds can have at most 25 fields")?;
+    check(
+        embed.fields.iter().all(|x| !x.name.is_empty() && x.name.len() <= 256),
+        "Field names must be between 1 and 256 characters long",
+    )?;
+    check(
+        embed.fields.iter().all(|x| !x.value.is_empty() && x.value.len() <= 1024),
+        "Field values must be between 1 and 1024 characters long",
+    )?;
+    let total_len = embed
+        .title
+        .iter()
+        .chain(std::iter::once(description))
+        .map(|x| x.chars().count())
+        .sum::<usize>()
+        + embed
+            .fields
+            .iter()
+            .map(|x| x.name.chars().count() + x.value.chars().count())
+            .sum::<usize>();
+    check(
+        total_len <= MAX_TAG_EMBED_TOTAL_LEN,
+        &format!("The embed's text must be at most {MAX_TAG_EMBED_TOTAL_LEN} characters in total"),
+    )?;
+    check(
+        embed.color.as_deref().is_none_or(|x| parse_embed_color(x).is_some()),
+        "The color must be given as #rrggbb",
+    )?;
+    check(
+        embed.images.len() <= MAX_TAG_EMBED_IMAGES,
+        &format!("Embeds can have at most {MAX_TAG_EMBED_IMAGES} images"),
+    )?;
+    check(
+        // Images of existing tags have already been replaced with fake-CDN ids
+        embed
+            .images
+            .iter()
+            .chain(embed.thumbnail.iter())
+            .all(|x| util::validate_url(x) || x.parse::<cdn_hack::FakeCdnId>().is_ok()),
+        "Images must be URLs",
+    )?;
+
+    let templated = std::iter::once(description)
+        .chain(embed.title.iter())
+        .chain(embed.fields.iter().map(|x| &x.value));
+    for text in templated {
+        tag_template::validate(text).with_user_error(|e| e.to_string())?;
+    }
+    Ok(parsed)
+}
+
+/// Re-upload all images of a rich embed tag, so they keep working after the original messages are gone.
+pub async fn persist_tag_embed_images(
+    ctx: &poise::serenity_prelude::Context,
+    mut embed: TagEmbed,
+    metadata: serde_json::Value,
+) -> anyhow::Result<TagEmbed> {
+    for image in embed.images.iter_mut().chain(embed.thumbnail.iter_mut()) {
+        *image = cdn_hack::persist_cdn_links_in_string(ctx, image, metadata.clone()).await?;
+    }
+    Ok(embed)
+}
+
+/// Custom-id prefixes of the buttons on tag proposals, followed by the id of the proposal.
+pub const TAG_PROPOSAL_APPROVE_BUTTON: &str = "tag-proposal-approve:";
+pub const TAG_PROPOSAL_APPROVE_OFFICIAL_BUTTON: &str = "tag-proposal-approve-official:";
+pub const TAG_PROPOSAL_EDIT_BUTTON: &str = "tag-proposal-edit:";
+pub const TAG_PROPOSAL_REJECT_BUTTON: &str = "tag-proposal-reject:";
+
+/// Custom-id prefix of the button that posts a suggested tag, followed by `<asker id>:<tag name>`.
+pub const TAG_SUGGESTION_SHOW_BUTTON: &str = "tag-suggestion-show:";
+/// Custom-id prefix of the button that dismisses a tag suggestion, followed by the id of the asker.
+pub const TAG_SUGGESTION_DISMISS_BUTTON: &str = "tag-suggestion-dismiss:";
+
+/// How many proposals a user may have waiting for review at the same time.
+const MAX_PENDING_TAG_PROPOSALS: i32 = 3;
+
+#[derive(Debug, poise::Modal)]
+#[name = "Tag proposal"]
+struct TagProposalModal {
+    #[name = "Name"]
+    #[placeholder = "Name of the tag"]
+    #[max_length = 50]
+    name: String,
+    #[name = "Content"]
+    #[placeholder = "Content of the tag. Can use {user}, {author}, {channel} and {arg1} to {arg9}"]
+    #[paragraph]
+    content: String,
+}
+
+/// Check that a proposed tag name can be used.
+pub fn validate_tag_proposal_name(name: &str) -> Result<(), UserErr> {
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err(UserErr::new("Tag names can't be empty or contain spaces"));
+    }
+    Ok(())
+}
+
+/// Suggest a new tag. It will be added once a moderator approves it.
+#[poise::command(slash_command, guild_only, rename = "proposetag")]
+pub async fn proposetag(app_ctx: AppCtx<'_>) -> Res<()> {
+    let ctx = Ctx::Application(app_ctx);
+    let config = ctx.get_config();
+    let db = ctx.get_db();
+
+    if db.count_pending_tag_proposals(ctx.author().id).await? >= MAX_PENDING_TAG_PROPOSALS {
+        abort_with!("You already have too many tag proposals waiting for review");
+    }
+
+    let result = TagProposalModal::execute(app_ctx)
+        .instrument(tracing::info_span!("wait for modal response"))
+        .await?
+        .context("Modal timed out")?;
+    let name = result.name.trim().to_string();
+    validate_tag_proposal_name(&name)?;
+    if db.get_tag(&name).await?.is_some() {
+        abort_with!("A tag with this name already exists");
+    }
+    tag_template::validate(&result.content).with_user_error(|e| e.to_string())?;
+
+    let content = cdn_hack::persist_cdn_links_in_string(
+        ctx.serenity_context(),
+        &result.content,
+        serde_json::json!({"kind": "tag", "tag_name": name}),
+    )
+    .await?;
+    let proposal = db.add_tag_proposal(name, content, ctx.author().id, Utc::now()).await?;
+
+    config
+        .channel_mod_bot_stuff
+        .send_message(
+            &ctx.serenity_context(),
+            CreateMessage::default()
+                .embed(tag_proposal_embed(embeds::base_embed(&ctx), &proposal))
+                .components(tag_proposal_buttons(proposal.id)),
+        )
+        .await?;
+
+    ctx.send(
+        CreateReply::default()
+            .content("Thanks! Your tag proposal will be reviewed by the moderators.")
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Render a tag proposal for the moderators to review.
+pub fn tag_proposal_embed(base_embed: CreateEmbed, proposal: &TagProposal) -> CreateEmbed {
+    base_embed
+        .title(format!("Tag proposal: {}", proposal.name))
+        .description(util::ellipsis_text(&proposal.content, 4000))
+        .field("Proposed by", proposal.user.mention().to_string(), true)
+        .timestamp(proposal.create_date)
+}
+
+pub fn tag_proposal_buttons(proposal_id: i64) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{TAG_PROPOSAL_APPROVE_BUTTON}{proposal_id}"))
+            .label("Approve")
+            .style(ButtonStyle::Success),
+        CreateButton::new(format!("{TAG_PROPOSAL_APPROVE_OFFICIAL_BUTTON}{proposal_id}"))
+            .label("Approve as official")
+            .style(ButtonStyle::Primary),
+        CreateButton::new(format!("{TAG_PROPOSAL_EDIT_BUTTON}{proposal_id}"))
+            .label("Edit")
+            .style(ButtonStyle::Secondary),
+        CreateButton::new(format!("{TAG_PROPOSAL_REJECT_BUTTON}{proposal_id}"))
+            .label("Reject")
+            .style(ButtonStyle::Danger),
+    ])]
+}
+
 /// Autocomplete all tags, but also provide whatever the user has already typed as one of the options.
 /// Used in /tag set, to provide completion for edits, but also allow adding new tags
 async fn tag_autocomplete(ctx: Ctx<'_>, partial: &str) -> impl Iterator<Item = String> {
@@ -132,10 +1106,53 @@ async fn tag_autocomplete(ctx: Ctx<'_>, partial: &str) -> impl Iterator<Item = S
     tag_autocomplete_existing(ctx, partial).await.chain(last).dedup() // when the partial fully matches a value, we otherwise get a duplicate
 }
 
-/// Autocomplete all tags
+/// Autocomplete all tags, matching on their names as well as their aliases,
+/// followed by tags whose content matches what was typed so far.
 async fn tag_autocomplete_existing(ctx: Ctx<'_>, partial: &str) -> impl Iterator<Item = String> {
     let db = ctx.get_db();
     let tags = db.list_tags().await.unwrap_or_default();
-    let partial = partial.to_ascii_lowercase();
-    tags.into_iter().filter(move |tag| tag.to_ascii_lowercase().starts_with(&partial))
+    let prefix_matches = tags
+        .into_iter()
+        .filter(|tag| tag.matches_prefix(partial))
+        .map(|tag| tag.name)
+        .collect_vec();
+    let search_matches = if partial.is_empty() || prefix_matches.len() >= 25 {
+        Vec::new()
+    } else {
+        db.search_tags(partial, 25).await.unwrap_or_default()
+    };
+    prefix_matches.into_iter().chain(search_matches.into_iter().map(|x| x.name)).unique().take(25)
+}
+
+/// Autocomplete all tag aliases
+async fn tag_alias_autocomplete(ctx: Ctx<'_>, partial: &str) -> impl Iterator<Item = String> {
+    let db = ctx.get_db();
+    let tags = db.list_tags().await.unwrap_or_default();
+    let partial = partial.to_lowercase();
+    tags.into_iter()
+        .flat_map(|tag| tag.aliases)
+        .filter(move |alias| alias.to_lowercase().starts_with(&partial))
+}
+
+/// Autocomplete all tag trigger patterns
+async fn tag_trigger_autocomplete(ctx: Ctx<'_>, partial: &str) -> impl Iterator<Item = String> {
+    let db = ctx.get_db();
+    let triggers = db.get_tag_triggers().await.unwrap_or_default();
+    let partial = partial.to_lowercase();
+    triggers
+        .into_iter()
+        .map(|x| x.pattern)
+        .filter(move |pattern| pattern.to_lowercase().contains(&partial))
+        .unique()
+}
+
+/// Autocomplete the categories already in use
+async fn tag_category_autocomplete(ctx: Ctx<'_>, partial: &str) -> impl Iterator<Item = String> {
+    let db = ctx.get_db();
+    let tags = db.list_tags().await.unwrap_or_default();
+    let partial = partial.to_lowercase();
+    tags.into_iter()
+        .filter_map(|tag| tag.category)
+        .unique()
+        .filter(move |category| category.to_lowercase().starts_with(&partial))
 }
@@ -0,0 +1,87 @@
+use poise::serenity_prelude::{ButtonStyle, CreateActionRow, CreateButton, CreateMessage, User};
+use robbb_util::embeds;
+
+use super::*;
+
+/// Custom id of the button members press to verify themselves.
+pub const VERIFICATION_BUTTON: &str = "verification-start";
+
+/// Manage the join verification
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    subcommands("verification_post", "verification_status")
+)]
+pub async fn verification(_ctx: Ctx<'_>) -> Res<()> {
+    Ok(())
+}
+
+/// Post the verification button in the rules channel
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "post"
+)]
+pub async fn verification_post(
+    ctx: Ctx<'_>,
+    #[description = "Text shown above the button"] text: Option<String>,
+) -> Res<()> {
+    let config = ctx.get_config();
+    if config.verification.is_none() {
+        abort_with!("Verification is not enabled");
+    }
+
+    let embed =
+        embeds::base_embed(&ctx).title("Verification").description(text.unwrap_or_else(|| {
+            "Please read the rules above, then press the button below to gain access to the server."
+                .to_string()
+        }));
+    let button = CreateButton::new(VERIFICATION_BUTTON)
+        .label("I have read the rules")
+        .style(ButtonStyle::Success);
+    config
+        .channel_rules
+        .send_message(
+            &ctx.serenity_context(),
+            CreateMessage::default()
+                .embed(embed)
+                .components(vec![CreateActionRow::Buttons(vec![button])]),
+        )
+        .await?;
+    ctx.say_success(format!(
+        "Posted the verification button in {}",
+        config.channel_rules.mention()
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Show the verification state of a member
+#[poise::command(
+    slash_command,
+    guild_only,
+    custom_data = "CmdMeta { perms: PermissionLevel::Mod }",
+    rename = "status"
+)]
+pub async fn verification_status(
+    ctx: Ctx<'_>,
+    #[description = "The member to check"] user: User,
+) -> Res<()> {
+    let db = ctx.get_db();
+    let verification =
+        db.get_verification(user.id).await?.user_error("No verification state for that user")?;
+
+    let state = match verification.verified_date {
+        Some(date) => format!("Verified {}", util::format_date_detailed(date)),
+        None => format!("Unverified, will be kicked {}", util::format_date(verification.deadline)),
+    };
+    let embed = embeds::base_embed(&ctx)
+        .title(format!("Verification of {}", user.tag()))
+        .description(state)
+        .field("Joined", util::format_date_detailed(verification.join_date), false)
+        .field("Failed attempts", verification.failed_attempts.to_string(), false);
+    ctx.reply_embed_ephemeral(embed).await?;
+    Ok(())
+}
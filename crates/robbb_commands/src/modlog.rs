@@ -127,24 +127,40 @@ pub async fn log_mute(
         .await;
 }
 
-pub async fn log_mute_for_spamming(
+/// Log a mute applied automatically by the bot. `reason` completes "User was muted for ...".
+pub async fn log_automute(
     ctx: &client::Context,
-    spam_msg: &Message,
+    context_msg: &Message,
     duration: std::time::Duration,
+    reason: &str,
 ) {
     ctx.log_bot_action(|e| {
         e.title("Automute")
-            .thumbnail(spam_msg.author.face())
+            .thumbnail(context_msg.author.face())
             .description(format!(
-                "User {} was muted for spamming\n{}",
-                spam_msg.author.mention_and_tag(),
-                spam_msg.to_context_link(),
+                "User {} was muted for {reason}\n{}",
+                context_msg.author.mention_and_tag(),
+                context_msg.to_context_link(),
             ))
             .field("Duration", humantime::Duration::from(duration).to_string(), false)
     })
     .await;
 }
 
+pub async fn log_honeypot_ban(ctx: &client::Context, context_msg: &Message) {
+    ctx.log_bot_action(|e| {
+        e.title("Honeypot ban")
+            .thumbnail(context_msg.author.face())
+            .description(format!(
+                "yote {} for posting in {}",
+                context_msg.author.mention_and_tag(),
+                context_msg.channel_id.mention(),
+            ))
+            .field("Message", util::ellipsis_text(&context_msg.content, 1000), false)
+    })
+    .await;
+}
+
 pub async fn log_user_mute_ended(ctx: &client::Context, mute: &Mute) {
     let user = mute.user.to_user(&ctx).await;
     ctx.log_bot_action(|e| {
@@ -1,18 +1,60 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use itertools::Itertools;
-use regex::{Regex, RegexBuilder};
+use regex::{Regex, RegexBuilder, RegexSetBuilder};
 use serenity::model::id::UserId;
 
 use super::Db;
 
+#[derive(Debug, Clone)]
+pub struct BlocklistEntry {
+    pub pattern: String,
+    pub added_by: UserId,
+    pub create_date: Option<DateTime<Utc>>,
+    pub stats: BlocklistStats,
+}
+
+/// Statistics about how often a pattern caused something to be blocked.
+/// Patterns are stored by their text, which means this may also
+/// contain entries for automod keywords that are not part of our blocklist.
+#[derive(Debug, Clone, Default)]
+pub struct BlocklistStats {
+    pub pattern: String,
+    pub hit_count: i64,
+    pub last_hit: Option<DateTime<Utc>>,
+    pub last_offender: Option<UserId>,
+}
+
 impl Db {
+    /// Get all blocklist patterns combined into a single regex.
+    /// The compiled regex is cached until the blocklist changes.
     pub async fn get_combined_blocklist_regex(&self) -> Result<Regex> {
+        if let Some(regex) = self.blocklist_regex_cache.read().await.as_ref() {
+            return Ok(regex.clone());
+        }
         let blocklist = self.get_blocklist().await?;
-        if blocklist.is_empty() {
-            Ok(Regex::new("a^").unwrap())
+        let regex = if blocklist.is_empty() {
+            Regex::new("a^").unwrap()
         } else {
-            Ok(RegexBuilder::new(&blocklist.join("|")).case_insensitive(true).build()?)
+            RegexBuilder::new(&blocklist.join("|")).case_insensitive(true).build()?
+        };
+        *self.blocklist_regex_cache.write().await = Some(regex.clone());
+        Ok(regex)
+    }
+
+    /// Find the first blocklist pattern that matches the given text.
+    /// Used to figure out which entry of the combined blocklist regex was responsible for a match.
+    /// The compiled patterns are cached until the blocklist changes.
+    #[tracing::instrument(skip_all)]
+    pub async fn find_blocklist_pattern(&self, text: &str) -> Result<Option<String>> {
+        if let Some((regex_set, blocklist)) = self.blocklist_regex_set_cache.read().await.as_ref() {
+            return Ok(regex_set.matches(text).iter().next().map(|idx| blocklist[idx].clone()));
         }
+        let blocklist = self.get_blocklist().await?;
+        let regex_set = RegexSetBuilder::new(&blocklist).case_insensitive(true).build()?;
+        let pattern = regex_set.matches(text).iter().next().map(|idx| blocklist[idx].clone());
+        *self.blocklist_regex_set_cache.write().await = Some((regex_set, blocklist));
+        Ok(pattern)
     }
 
     #[tracing::instrument(skip_all)]
@@ -30,22 +72,91 @@ impl Db {
         }
     }
 
+    /// Get all blocklist entries, together with their hit statistics.
+    #[tracing::instrument(skip_all)]
+    pub async fn get_blocklist_entries(&self) -> Result<Vec<BlocklistEntry>> {
+        Ok(sqlx::query!(
+            r#"select blocked_regexes.pattern as "pattern!", added_by, create_date,
+                    hit_count as "hit_count?", last_hit, last_offender
+               from blocked_regexes
+               left join blocklist_stats on blocklist_stats.pattern = blocked_regexes.pattern"#
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|x| BlocklistEntry {
+            added_by: UserId::new(x.added_by as u64),
+            create_date: x.create_date.map(|date| DateTime::from_naive_utc_and_offset(date, Utc)),
+            stats: BlocklistStats {
+                pattern: x.pattern.clone(),
+                hit_count: x.hit_count.unwrap_or_default(),
+                last_hit: x.last_hit.map(|date| DateTime::from_naive_utc_and_offset(date, Utc)),
+                last_offender: x.last_offender.map(|x| UserId::new(x as u64)),
+            },
+            pattern: x.pattern,
+        })
+        .collect())
+    }
+
+    /// Get the hit statistics of all patterns, including ones that aren't in the blocklist.
+    #[tracing::instrument(skip_all)]
+    pub async fn get_blocklist_stats(&self) -> Result<Vec<BlocklistStats>> {
+        Ok(sqlx::query!(
+            r#"select pattern as "pattern!", hit_count, last_hit, last_offender from blocklist_stats"#
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|x| BlocklistStats {
+            pattern: x.pattern,
+            hit_count: x.hit_count,
+            last_hit: x.last_hit.map(|date| DateTime::from_naive_utc_and_offset(date, Utc)),
+            last_offender: x.last_offender.map(|x| UserId::new(x as u64)),
+        })
+        .collect())
+    }
+
+    #[tracing::instrument(skip_all, fields(blocklist.pattern = %pattern, blocklist.offender = %offender))]
+    pub async fn record_blocklist_hit(&self, pattern: &str, offender: UserId) -> Result<()> {
+        let offender: i64 = offender.into();
+        let now = Utc::now();
+        sqlx::query!(
+            "insert into blocklist_stats (pattern, hit_count, last_hit, last_offender) values (?1, 1, ?2, ?3)
+                on conflict(pattern) do update set hit_count=hit_count + 1, last_hit=?2, last_offender=?3",
+            pattern,
+            now,
+            offender,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
     pub async fn add_blocklist_entry(&self, user_id: UserId, s: &str) -> Result<()> {
         let user_id: i64 = user_id.into();
-        sqlx::query!("insert into blocked_regexes(pattern, added_by) values (?, ?)", s, user_id)
-            .execute(&self.pool)
-            .await?;
+        let now = Utc::now();
+        sqlx::query!(
+            "insert into blocked_regexes(pattern, added_by, create_date) values (?, ?, ?)",
+            s,
+            user_id,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
 
         let mut cache = self.blocklist_cache.write().await;
         if let Some(ref mut cache) = cache.as_mut() {
             cache.push(s.to_string());
         }
+        *self.blocklist_regex_cache.write().await = None;
+        *self.blocklist_regex_set_cache.write().await = None;
 
         Ok(())
     }
 
     pub async fn remove_blocklist_entry(&self, s: &str) -> Result<()> {
         sqlx::query!("delete from blocked_regexes where pattern=?", s).execute(&self.pool).await?;
+        sqlx::query!("delete from blocklist_stats where pattern=?", s).execute(&self.pool).await?;
 
         let mut cache = self.blocklist_cache.write().await;
         if let Some(ref mut cache) = cache.as_mut() {
@@ -53,6 +164,8 @@ impl Db {
                 cache.remove(pos);
             }
         }
+        *self.blocklist_regex_cache.write().await = None;
+        *self.blocklist_regex_set_cache.write().await = None;
 
         Ok(())
     }
@@ -0,0 +1,132 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serenity::model::id::UserId;
+
+use super::Db;
+
+#[derive(Debug, Eq, Copy, Clone, PartialEq, Hash, poise::ChoiceParameter)]
+pub enum DomainFilterKind {
+    #[name = "Deny"]
+    Deny,
+    #[name = "Allow"]
+    Allow,
+}
+
+impl std::fmt::Display for DomainFilterKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DomainFilterKind::Deny => write!(f, "Deny"),
+            DomainFilterKind::Allow => write!(f, "Allow"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DomainFilterEntry {
+    /// Either a plain domain like `example.com`, or a wildcard like `*.example.com`,
+    /// which matches `example.com` and all of its subdomains.
+    pub domain: String,
+    pub kind: DomainFilterKind,
+    pub added_by: UserId,
+    pub create_date: Option<DateTime<Utc>>,
+}
+
+impl DomainFilterEntry {
+    pub fn matches_host(&self, host: &str) -> bool {
+        match self.domain.strip_prefix("*.") {
+            Some(base) => host == base || host.strip_suffix(base).is_some_and(|x| x.ends_with('.')),
+            None => host == self.domain,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DomainFilter {
+    pub entries: Vec<DomainFilterEntry>,
+}
+
+impl DomainFilter {
+    /// Find the entry responsible for the given host.
+    /// If multiple entries match, the most specific one wins, with allow-entries winning ties.
+    pub fn check_host(&self, host: &str) -> Option<&DomainFilterEntry> {
+        let host = host.trim_end_matches('.').to_lowercase();
+        self.entries.iter().filter(|x| x.matches_host(&host)).max_by_key(|x| {
+            (x.domain.trim_start_matches("*.").len(), x.kind == DomainFilterKind::Allow)
+        })
+    }
+}
+
+/// Normalize a user provided domain pattern, returning `None` if it is not a valid domain or wildcard domain.
+pub fn normalize_domain_pattern(pattern: &str) -> Option<String> {
+    let pattern = pattern.trim().trim_end_matches('.').to_lowercase();
+    let domain = pattern.strip_prefix("*.").unwrap_or(&pattern);
+    let valid = domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        });
+    valid.then_some(pattern)
+}
+
+impl Db {
+    #[tracing::instrument(skip_all)]
+    pub async fn get_domain_filter(&self) -> Result<DomainFilter> {
+        let mut cache = self.domain_filter_cache.write().await;
+        if let Some(cache) = cache.as_ref() {
+            return Ok(cache.clone());
+        }
+
+        let entries = sqlx::query!(
+            r#"select domain as "domain!", allowed, added_by, create_date from domain_filter"#
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|x| DomainFilterEntry {
+            domain: x.domain,
+            kind: if x.allowed { DomainFilterKind::Allow } else { DomainFilterKind::Deny },
+            added_by: UserId::new(x.added_by as u64),
+            create_date: x.create_date.map(|date| DateTime::from_naive_utc_and_offset(date, Utc)),
+        })
+        .collect();
+        let filter = DomainFilter { entries };
+        *cache = Some(filter.clone());
+        Ok(filter)
+    }
+
+    /// Add a domain to the filter, replacing any existing entry for the same domain.
+    #[tracing::instrument(skip_all, fields(domain_filter.domain = %domain, domain_filter.kind = %kind))]
+    pub async fn set_domain_filter_entry(
+        &self,
+        added_by: UserId,
+        domain: &str,
+        kind: DomainFilterKind,
+    ) -> Result<()> {
+        let added_by: i64 = added_by.into();
+        let allowed = kind == DomainFilterKind::Allow;
+        let now = Utc::now();
+        sqlx::query!(
+            "insert or replace into domain_filter (domain, allowed, added_by, create_date) values (?, ?, ?, ?)",
+            domain,
+            allowed,
+            added_by,
+            now,
+        )
+        .execute(&self.pool)
+        .await?;
+        *self.domain_filter_cache.write().await = None;
+        Ok(())
+    }
+
+    /// Remove a domain from the filter. Returns false if there was no such entry.
+    #[tracing::instrument(skip_all, fields(domain_filter.domain = %domain))]
+    pub async fn remove_domain_filter_entry(&self, domain: &str) -> Result<bool> {
+        let result = sqlx::query!("delete from domain_filter where domain=?", domain)
+            .execute(&self.pool)
+            .await?;
+        *self.domain_filter_cache.write().await = None;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -8,7 +8,7 @@ use serenity::model::id::UserId;
 use super::fetch_field::{FetchField, FETCH_KEY_ORDER};
 use super::Db;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Fetch {
     pub user: UserId,
     pub info: HashMap<FetchField, String>,
@@ -28,6 +28,32 @@ impl Fetch {
     }
 }
 
+/// How a single field differs between two fetches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FetchFieldChange {
+    Added(FetchField, String),
+    Removed(FetchField, String),
+    Changed(FetchField, String, String),
+}
+
+/// List the fields that differ between two fetches, in the order they are shown in.
+pub fn diff_fetch_info(
+    old: &HashMap<FetchField, String>,
+    new: &HashMap<FetchField, String>,
+) -> Vec<FetchFieldChange> {
+    FETCH_KEY_ORDER
+        .iter()
+        .filter_map(|field| match (old.get(field), new.get(field)) {
+            (None, Some(new)) => Some(FetchFieldChange::Added(field.clone(), new.clone())),
+            (Some(old), None) => Some(FetchFieldChange::Removed(field.clone(), old.clone())),
+            (Some(old), Some(new)) if old != new => {
+                Some(FetchFieldChange::Changed(field.clone(), old.clone(), new.clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
 impl Db {
     #[tracing::instrument(skip_all)]
     pub async fn set_fetch(
@@ -38,16 +64,37 @@ impl Db {
     ) -> Result<Fetch> {
         {
             let user: i64 = user.into();
-            let info = serde_json::to_string(&info)?;
+            let info_json = serde_json::to_string(&info)?;
+            let mut transaction = self.pool.begin().await?;
+
+            // Keep the setup that is being replaced, unless nothing actually changed
+            let previous = sqlx::query!("select info, create_date from fetch where usr=?", user)
+                .fetch_optional(&mut *transaction)
+                .await?;
+            if let Some(previous) = previous {
+                let previous_info: HashMap<FetchField, String> =
+                    serde_json::from_str(&previous.info).unwrap_or_default();
+                if !previous_info.is_empty() && previous_info != info {
+                    sqlx::query!(
+                        "insert into fetch_history (usr, info, create_date) values (?, ?, ?)",
+                        user,
+                        previous.info,
+                        previous.create_date,
+                    )
+                    .execute(&mut *transaction)
+                    .await?;
+                }
+            }
 
             sqlx::query!(
                 "insert into fetch (usr, info, create_date) values (?1, ?2, ?3) on conflict(usr) do update set info=?2, create_date=?3",
                 user,
-                info,
+                info_json,
                 create_date,
             )
-            .execute(&self.pool)
+            .execute(&mut *transaction)
             .await?;
+            transaction.commit().await?;
         }
 
         Ok(Fetch { user, info, create_date })
@@ -107,4 +154,42 @@ impl Db {
             })
             .collect::<Result<_>>()
     }
+
+    /// Remove a users fetch together with all of their previous setups.
+    #[tracing::instrument(skip_all)]
+    pub async fn clear_fetch(&self, user: UserId) -> Result<()> {
+        let user: i64 = user.into();
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query!("delete from fetch where usr=?", user).execute(&mut *transaction).await?;
+        sqlx::query!("delete from fetch_history where usr=?", user)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    /// Get the previous setups of a user, newest first, not including their current fetch.
+    #[tracing::instrument(skip_all)]
+    pub async fn get_fetch_history(&self, user: UserId, limit: i64) -> Result<Vec<Fetch>> {
+        let user_id: i64 = user.into();
+        sqlx::query!(
+            "select info, create_date from fetch_history where usr=? order by id desc limit ?",
+            user_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|x| {
+            let create_date = x
+                .create_date
+                .map(|date| chrono::DateTime::from_naive_utc_and_offset(date, chrono::Utc));
+            Ok(Fetch {
+                user,
+                info: serde_json::from_str(&x.info).context("Failed to deserialize fetch data")?,
+                create_date,
+            })
+        })
+        .collect::<Result<_>>()
+    }
 }
@@ -0,0 +1,136 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use serenity::model::id::{ChannelId, UserId};
+
+use super::Db;
+
+/// Channels, categories and authors users don't want to get highlighted for.
+#[derive(Debug, Clone, Default)]
+pub struct HighlightIgnores {
+    pub ignored_channels: HashMap<UserId, HashSet<ChannelId>>,
+    pub blocked_users: HashMap<UserId, HashSet<UserId>>,
+}
+
+impl HighlightIgnores {
+    /// Check if the user ignores highlights from the given channel (or its category), or by the given author.
+    pub fn is_ignored(
+        &self,
+        user: UserId,
+        channel: ChannelId,
+        category: Option<ChannelId>,
+        author: UserId,
+    ) -> bool {
+        let ignores_channel = self.ignored_channels.get(&user).is_some_and(|channels| {
+            channels.contains(&channel) || category.is_some_and(|x| channels.contains(&x))
+        });
+        ignores_channel || self.blocked_users.get(&user).is_some_and(|x| x.contains(&author))
+    }
+}
+
+impl Db {
+    #[tracing::instrument(skip_all)]
+    pub async fn get_highlight_ignores(&self) -> Result<HighlightIgnores> {
+        let mut cache = self.highlight_ignore_cache.write().await;
+        if let Some(cache) = cache.as_ref() {
+            return Ok(cache.clone());
+        }
+
+        let mut ignores = HighlightIgnores::default();
+        for x in
+            sqlx::query!("select * from highlight_ignored_channel").fetch_all(&self.pool).await?
+        {
+            ignores
+                .ignored_channels
+                .entry(UserId::new(x.usr as u64))
+                .or_default()
+                .insert(ChannelId::new(x.channel as u64));
+        }
+        for x in sqlx::query!("select * from highlight_blocked_user").fetch_all(&self.pool).await? {
+            ignores
+                .blocked_users
+                .entry(UserId::new(x.usr as u64))
+                .or_default()
+                .insert(UserId::new(x.blocked_usr as u64));
+        }
+        *cache = Some(ignores.clone());
+        Ok(ignores)
+    }
+
+    /// Ignore or stop ignoring a channel or category for a users highlights.
+    /// Returns true if the channel is now ignored.
+    #[tracing::instrument(skip_all, fields(user.id = %user, channel.id = %channel))]
+    pub async fn toggle_highlight_ignored_channel(
+        &self,
+        user: UserId,
+        channel: ChannelId,
+    ) -> Result<bool> {
+        let user: i64 = user.into();
+        let channel: i64 = channel.into();
+        let removed = sqlx::query!(
+            "delete from highlight_ignored_channel where usr=? and channel=?",
+            user,
+            channel
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected()
+            > 0;
+        if !removed {
+            sqlx::query!(
+                "insert into highlight_ignored_channel (usr, channel) values (?, ?)",
+                user,
+                channel
+            )
+            .execute(&self.pool)
+            .await?;
+        }
+        *self.highlight_ignore_cache.write().await = None;
+        Ok(!removed)
+    }
+
+    /// Block or unblock an author for a users highlights.
+    /// Returns true if the author is now blocked.
+    #[tracing::instrument(skip_all, fields(user.id = %user, blocked_user.id = %blocked_user))]
+    pub async fn toggle_highlight_blocked_user(
+        &self,
+        user: UserId,
+        blocked_user: UserId,
+    ) -> Result<bool> {
+        let user: i64 = user.into();
+        let blocked_user: i64 = blocked_user.into();
+        let removed = sqlx::query!(
+            "delete from highlight_blocked_user where usr=? and blocked_usr=?",
+            user,
+            blocked_user
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected()
+            > 0;
+        if !removed {
+            sqlx::query!(
+                "insert into highlight_blocked_user (usr, blocked_usr) values (?, ?)",
+                user,
+                blocked_user
+            )
+            .execute(&self.pool)
+            .await?;
+        }
+        *self.highlight_ignore_cache.write().await = None;
+        Ok(!removed)
+    }
+
+    #[tracing::instrument(skip_all, fields(user.id = %user))]
+    pub async fn rm_highlight_ignores_of(&self, user: UserId) -> Result<()> {
+        let user: i64 = user.into();
+        sqlx::query!("delete from highlight_ignored_channel where usr=?", user)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query!("delete from highlight_blocked_user where usr=?", user)
+            .execute(&self.pool)
+            .await?;
+        *self.highlight_ignore_cache.write().await = None;
+        Ok(())
+    }
+}
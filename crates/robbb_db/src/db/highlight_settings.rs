@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Timelike, Utc};
+use serenity::model::id::UserId;
+
+use super::Db;
+
+/// How a user wants to be notified about their highlights.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighlightSettings {
+    /// Don't notify again about highlights in a channel within this many minutes of the last one there.
+    pub cooldown_minutes: i64,
+    /// Don't notify about highlights in channels the user recently wrote in themselves.
+    pub suppress_when_active: bool,
+    /// Hours of the day (start inclusive, end exclusive) in which notifications are held back.
+    pub quiet_hours: Option<(u32, u32)>,
+    /// Offset of the users timezone, used to interpret `quiet_hours`.
+    pub utc_offset_hours: i64,
+    /// If set, notifications are batched into a single DM every this many minutes.
+    pub digest_minutes: Option<i64>,
+}
+
+/// The defaults notify about every highlight, like before these settings existed.
+impl Default for HighlightSettings {
+    fn default() -> Self {
+        Self {
+            cooldown_minutes: 0,
+            suppress_when_active: false,
+            quiet_hours: None,
+            utc_offset_hours: 0,
+            digest_minutes: None,
+        }
+    }
+}
+
+impl HighlightSettings {
+    pub fn is_quiet_at(&self, time: DateTime<Utc>) -> bool {
+        let Some((start, end)) = self.quiet_hours else { return false };
+        let hour = (time.hour() as i64 + self.utc_offset_hours).rem_euclid(24) as u32;
+        if start <= end {
+            start <= hour && hour < end
+        } else {
+            hour >= start || hour < end
+        }
+    }
+}
+
+impl Db {
+    /// Get the highlight settings of all users that changed them from the defaults.
+    #[tracing::instrument(skip_all)]
+    pub async fn get_highlight_settings(&self) -> Result<HashMap<UserId, HighlightSettings>> {
+        let mut cache = self.highlight_settings_cache.write().await;
+        if let Some(cache) = cache.as_ref() {
+            return Ok(cache.clone());
+        }
+        let settings: HashMap<_, _> = sqlx::query!("select * from highlight_settings")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| {
+                let quiet_hours = x
+                    .quiet_hours_start
+                    .zip(x.quiet_hours_end)
+                    .map(|(start, end)| (start as u32, end as u32));
+                let settings = HighlightSettings {
+                    cooldown_minutes: x.cooldown_minutes,
+                    suppress_when_active: x.suppress_when_active,
+                    quiet_hours,
+                    utc_offset_hours: x.utc_offset_hours,
+                    digest_minutes: x.digest_minutes,
+                };
+                (UserId::new(x.usr as u64), settings)
+            })
+            .collect();
+        *cache = Some(settings.clone());
+        Ok(settings)
+    }
+
+    #[tracing::instrument(skip_all, fields(user.id = %user))]
+    pub async fn get_highlight_settings_of(&self, user: UserId) -> Result<HighlightSettings> {
+        Ok(self.get_highlight_settings().await?.remove(&user).unwrap_or_default())
+    }
+
+    #[tracing::instrument(skip_all, fields(user.id = %user))]
+    pub async fn set_highlight_settings(
+        &self,
+        user: UserId,
+        settings: &HighlightSettings,
+    ) -> Result<()> {
+        let user: i64 = user.into();
+        let quiet_hours_start = settings.quiet_hours.map(|(start, _)| start as i64);
+        let quiet_hours_end = settings.quiet_hours.map(|(_, end)| end as i64);
+        sqlx::query!(
+            "insert or replace into highlight_settings
+                (usr, cooldown_minutes, suppress_when_active, quiet_hours_start, quiet_hours_end, utc_offset_hours, digest_minutes)
+             values (?, ?, ?, ?, ?, ?, ?)",
+            user,
+            settings.cooldown_minutes,
+            settings.suppress_when_active,
+            quiet_hours_start,
+            quiet_hours_end,
+            settings.utc_offset_hours,
+            settings.digest_minutes,
+        )
+        .execute(&self.pool)
+        .await?;
+        *self.highlight_settings_cache.write().await = None;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, fields(user.id = %user))]
+    pub async fn rm_highlight_settings_of(&self, user: UserId) -> Result<()> {
+        let user: i64 = user.into();
+        sqlx::query!("delete from highlight_settings where usr=?", user)
+            .execute(&self.pool)
+            .await?;
+        *self.highlight_settings_cache.write().await = None;
+        Ok(())
+    }
+}
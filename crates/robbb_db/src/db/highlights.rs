@@ -2,82 +2,228 @@ use super::*;
 use itertools::Itertools;
 use std::collections::HashSet;
 
-fn combine_multitrigger_regex<'a, I: IntoIterator<Item = &'a str>>(
-    words: I,
-) -> Result<regex::Regex> {
-    let joined_words = words.into_iter().map(regex::escape).join("|");
-    let mut regex_builder = regex::RegexBuilder::new(&format!(r"\b(?:{})\b", joined_words));
-    regex_builder
-        .case_insensitive(true)
-        .build()
-        .context("Failed to compile highlight trigger regex")
+/// Maximum length of a regex highlight.
+pub const MAX_HIGHLIGHT_REGEX_LEN: usize = 100;
+/// Maximum compiled size of a single regex highlight, to keep the combined matcher fast.
+const HIGHLIGHT_REGEX_SIZE_LIMIT: usize = 1 << 16;
+
+#[derive(Debug, Eq, Copy, Clone, PartialEq, Hash, poise::ChoiceParameter)]
+pub enum HighlightMode {
+    #[name = "Whole word"]
+    Word,
+    #[name = "Substring"]
+    Substring,
+    #[name = "Regex"]
+    Regex,
+}
+
+impl std::fmt::Display for HighlightMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HighlightMode::Word => write!(f, "Whole word"),
+            HighlightMode::Substring => write!(f, "Substring"),
+            HighlightMode::Regex => write!(f, "Regex"),
+        }
+    }
+}
+
+impl HighlightMode {
+    pub fn from_i64(n: i64) -> Result<Self> {
+        match n {
+            0 => Ok(HighlightMode::Word),
+            1 => Ok(HighlightMode::Substring),
+            2 => Ok(HighlightMode::Regex),
+            _ => bail!("Invalid highlight mode: {}", n),
+        }
+    }
+
+    pub fn as_i64(&self) -> i64 {
+        match self {
+            HighlightMode::Word => 0,
+            HighlightMode::Substring => 1,
+            HighlightMode::Regex => 2,
+        }
+    }
+}
+
+/// A highlight trigger. Words and substrings are matched case-insensitively and stored lowercase,
+/// regexes are case-insensitive by default, but kept as they were written.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HighlightTrigger {
+    pub word: String,
+    pub mode: HighlightMode,
+}
+
+impl HighlightTrigger {
+    pub fn new(word: &str, mode: HighlightMode) -> Self {
+        let word = match mode {
+            HighlightMode::Regex => word.to_string(),
+            HighlightMode::Word | HighlightMode::Substring => word.to_lowercase(),
+        };
+        HighlightTrigger { word, mode }
+    }
+
+    fn pattern(&self) -> String {
+        match self.mode {
+            HighlightMode::Word => format!(r"(?i)\b{}\b", regex::escape(&self.word)),
+            HighlightMode::Substring => format!("(?i){}", regex::escape(&self.word)),
+            HighlightMode::Regex => format!("(?i){}", self.word),
+        }
+    }
+
+    /// Compile the trigger, making sure regex triggers stay within the complexity limits.
+    pub fn compile(&self) -> Result<regex::Regex> {
+        if self.mode == HighlightMode::Regex && self.word.len() > MAX_HIGHLIGHT_REGEX_LEN {
+            bail!("Regex is longer than {} characters", MAX_HIGHLIGHT_REGEX_LEN);
+        }
+        let regex = regex::RegexBuilder::new(&self.pattern())
+            .size_limit(HIGHLIGHT_REGEX_SIZE_LIMIT)
+            .build()
+            .context("Failed to compile highlight trigger regex")?;
+        if regex.is_match("") {
+            bail!("Highlight trigger matches empty text");
+        }
+        Ok(regex)
+    }
+
+    /// Compile the trigger and make sure it isn't so broad that it would match almost every message.
+    /// Whole words are checked against the list of common words, while substrings and regexes
+    /// must not match any common word or ordinary text.
+    pub fn validate(&self) -> Result<regex::Regex> {
+        let regex = self.compile()?;
+        let too_common = match self.mode {
+            HighlightMode::Word => BLOCKED_WORDS.contains(&unicase::Ascii::new(self.word.as_str())),
+            HighlightMode::Substring | HighlightMode::Regex => {
+                regex.is_match(COMMON_TEXT_SAMPLE)
+                    || BLOCKED_WORDS.iter().any(|word| regex.is_match(word))
+            }
+        };
+        if too_common {
+            bail!("Highlight trigger {} would match too many messages", self);
+        }
+        Ok(regex)
+    }
+}
+
+impl std::fmt::Display for HighlightTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.mode {
+            HighlightMode::Word => write!(f, "{}", self.word),
+            HighlightMode::Substring => write!(f, "{} (substring)", self.word),
+            HighlightMode::Regex => write!(f, "/{}/ (regex)", self.word),
+        }
+    }
+}
+
+/// A highlight trigger that was found in a message.
+#[derive(Debug, Clone)]
+pub struct HighlightMatch {
+    pub trigger: HighlightTrigger,
+    /// The text in the message that matched the trigger
+    pub matched: String,
+    /// Byte range of the matched text within the message
+    pub range: std::ops::Range<usize>,
+    pub users: Vec<UserId>,
+}
+
+/// All triggers combined into a single [`regex::RegexSet`], so a message only has to be scanned once.
+/// The individual regexes are only used to extract the matched text once we know a trigger matched.
+#[derive(Debug, Clone)]
+struct HighlightMatcher {
+    set: regex::RegexSet,
+    triggers: Vec<(HighlightTrigger, regex::Regex)>,
+}
+
+impl HighlightMatcher {
+    fn build<'a>(triggers: impl IntoIterator<Item = &'a HighlightTrigger>) -> Self {
+        let triggers = triggers
+            .into_iter()
+            .filter_map(|trigger| match trigger.compile() {
+                Ok(regex) => Some((trigger.clone(), regex)),
+                Err(err) => {
+                    tracing::warn!(highlight.trigger = %trigger, error.message = %err, "Skipping invalid highlight trigger");
+                    None
+                }
+            })
+            .collect_vec();
+        let set = regex::RegexSetBuilder::new(triggers.iter().map(|(_, regex)| regex.as_str()))
+            .size_limit(HIGHLIGHT_REGEX_SIZE_LIMIT * triggers.len().max(1))
+            .build()
+            .unwrap_or_else(|err| {
+                tracing::error!(error.message = %err, "Failed to combine highlight triggers");
+                regex::RegexSet::empty()
+            });
+        HighlightMatcher { set, triggers }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct HighlightsData {
-    pub entries: HashMap<String, Vec<UserId>>,
-    combined_regex: regex::Regex,
+    pub entries: HashMap<HighlightTrigger, Vec<UserId>>,
+    matcher: HighlightMatcher,
 }
 
 impl HighlightsData {
-    pub fn from_entries(entries: Vec<(String, Vec<UserId>)>) -> Result<Self> {
-        let triggers_regex = combine_multitrigger_regex(entries.iter().map(|(x, _)| x.as_str()))?;
-        Ok(HighlightsData {
-            entries: entries
-                .into_iter()
-                .map(|(trigger, users)| (trigger.to_lowercase(), users))
-                .collect(),
-            combined_regex: triggers_regex,
-        })
+    pub fn from_entries(entries: Vec<(HighlightTrigger, Vec<UserId>)>) -> Self {
+        let entries: HashMap<_, _> = entries.into_iter().collect();
+        let matcher = HighlightMatcher::build(entries.keys());
+        HighlightsData { entries, matcher }
     }
 
     #[tracing::instrument(skip_all, fields(msg.content = %s))]
-    pub fn get_triggers_for_message(&self, s: &str) -> Vec<(String, Vec<UserId>)> {
-        self.combined_regex
-            .find_iter(s)
-            .filter_map(|m| {
-                let trigger = m.as_str();
-                Some((trigger.to_string(), self.entries.get(&trigger.to_lowercase())?.clone()))
+    pub fn get_triggers_for_message(&self, s: &str) -> Vec<HighlightMatch> {
+        self.matcher
+            .set
+            .matches(s)
+            .into_iter()
+            .filter_map(|idx| {
+                let (trigger, regex) = &self.matcher.triggers[idx];
+                let found = regex.find(s)?;
+                Some(HighlightMatch {
+                    trigger: trigger.clone(),
+                    matched: found.as_str().to_string(),
+                    range: found.range(),
+                    users: self.entries.get(trigger)?.clone(),
+                })
             })
             .collect()
     }
 
     #[tracing::instrument(skip_all)]
-    pub fn triggers_for_user(&self, user_id: UserId) -> impl Iterator<Item = &str> {
+    pub fn triggers_for_user(&self, user_id: UserId) -> impl Iterator<Item = &HighlightTrigger> {
         self.entries
             .iter()
             .filter(move |(_, users)| users.contains(&user_id))
-            .map(|(trigger, _)| trigger.as_str())
+            .map(|(trigger, _)| trigger)
     }
 
-    fn remove_entry(&mut self, trigger: &str, user: UserId) -> Result<()> {
-        let user_list =
-            self.entries.get_mut(&trigger.to_lowercase()).context("No entry with that trigger")?;
+    /// Find the trigger of the given user with the given text, regardless of its mode.
+    pub fn find_trigger_of_user(&self, user_id: UserId, word: &str) -> Option<&HighlightTrigger> {
+        self.triggers_for_user(user_id).find(|trigger| match trigger.mode {
+            HighlightMode::Regex => trigger.word == word,
+            HighlightMode::Word | HighlightMode::Substring => trigger.word == word.to_lowercase(),
+        })
+    }
+
+    fn remove_entry(&mut self, trigger: &HighlightTrigger, user: UserId) -> Result<()> {
+        let user_list = self.entries.get_mut(trigger).context("No entry with that trigger")?;
         user_list.retain(|x| x != &user);
         if user_list.is_empty() {
-            self.entries.remove(&trigger.to_lowercase());
-            self.combined_regex =
-                combine_multitrigger_regex(self.entries.keys().map(|x| x.as_str()))?;
+            self.entries.remove(trigger);
+            self.matcher = HighlightMatcher::build(self.entries.keys());
         }
         Ok(())
     }
 
-    fn add_entry(&mut self, trigger: String, user: UserId) -> Result<()> {
-        let already_in_regex = self.entries.contains_key(&trigger.to_lowercase());
-        self.entries
-            .entry(trigger)
-            .and_modify(|f| {
-                f.push(user);
-            })
-            .or_insert_with(|| vec![user]);
-        if !already_in_regex {
-            self.combined_regex =
-                combine_multitrigger_regex(self.entries.keys().map(|x| x.as_str()))?;
+    fn add_entry(&mut self, trigger: HighlightTrigger, user: UserId) {
+        let already_in_matcher = self.entries.contains_key(&trigger);
+        self.entries.entry(trigger).or_default().push(user);
+        if !already_in_matcher {
+            self.matcher = HighlightMatcher::build(self.entries.keys());
         }
-        Ok(())
     }
 
-    fn remove_entries_of(&mut self, user: UserId) -> Result<()> {
+    fn remove_entries_of(&mut self, user: UserId) {
         for users in self.entries.values_mut() {
             users.retain(|u| u != &user);
         }
@@ -85,12 +231,10 @@ impl HighlightsData {
 
         self.entries.retain(|_, users| !users.is_empty());
 
-        // update the regex if some words have been removed
+        // update the matcher if some words have been removed
         if self.entries.len() != old_length {
-            self.combined_regex =
-                combine_multitrigger_regex(self.entries.keys().map(|x| x.as_str()))?;
+            self.matcher = HighlightMatcher::build(self.entries.keys());
         }
-        Ok(())
     }
 }
 
@@ -105,48 +249,61 @@ impl Db {
                 .fetch_all(&self.pool)
                 .await?
                 .into_iter()
-                .map(|x| (x.word, UserId::from(x.usr as u64)))
+                .map(|x| {
+                    let mode = HighlightMode::from_i64(x.mode)?;
+                    Ok((HighlightTrigger::new(&x.word, mode), UserId::from(x.usr as u64)))
+                })
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
                 .into_group_map();
 
-            let highlight_data = HighlightsData::from_entries(entries.into_iter().collect())?;
+            let highlight_data = HighlightsData::from_entries(entries.into_iter().collect());
             cache.replace(highlight_data.clone());
             Ok(highlight_data)
         }
     }
 
     #[tracing::instrument(skip_all)]
-    pub async fn remove_highlight(&self, user: UserId, trigger: String) -> Result<()> {
+    pub async fn remove_highlight(&self, user: UserId, trigger: &HighlightTrigger) -> Result<()> {
         {
             let user: i64 = user.into();
-            sqlx::query!("delete from highlights where word=? and usr=?", trigger, user)
-                .execute(&self.pool)
-                .await?;
+            let mode = trigger.mode.as_i64();
+            sqlx::query!(
+                "delete from highlights where word=? and usr=? and mode=?",
+                trigger.word,
+                user,
+                mode
+            )
+            .execute(&self.pool)
+            .await?;
         }
         let mut cache = self.highlight_cache.write().await;
         if let Some(ref mut cache) = cache.as_mut() {
-            cache.remove_entry(&trigger, user)?;
+            cache.remove_entry(trigger, user)?;
         }
         Ok(())
     }
 
     #[tracing::instrument(skip_all)]
-    pub async fn set_highlight(&self, user: UserId, word: String) -> Result<()> {
-        if BLOCKED_WORDS.contains(&unicase::Ascii::new(word.as_str())) {
-            bail!(
-                "Refused to set a highlight for common word {} (requested by user {})",
-                word,
-                user
-            );
-        }
+    pub async fn set_highlight(&self, user: UserId, trigger: HighlightTrigger) -> Result<()> {
+        trigger
+            .validate()
+            .with_context(|| format!("Refused to set highlight (requested by user {})", user))?;
         {
             let user: i64 = user.into();
-            sqlx::query!("insert into highlights (word, usr) values (?, ?)", word, user)
-                .execute(&self.pool)
-                .await?;
+            let mode = trigger.mode.as_i64();
+            sqlx::query!(
+                "insert into highlights (word, usr, mode) values (?, ?, ?)",
+                trigger.word,
+                user,
+                mode
+            )
+            .execute(&self.pool)
+            .await?;
         }
         let mut cache = self.highlight_cache.write().await;
         if let Some(ref mut cache) = cache.as_mut() {
-            cache.add_entry(word, user)?;
+            cache.add_entry(trigger, user);
         }
 
         Ok(())
@@ -161,7 +318,7 @@ impl Db {
 
         let mut cache = self.highlight_cache.write().await;
         if let Some(ref mut cache) = cache.as_mut() {
-            cache.remove_entries_of(user)?;
+            cache.remove_entries_of(user);
         }
         Ok(())
     }
@@ -180,6 +337,11 @@ impl Db {
     }
 }
 
+/// Ordinary chat text that substring and regex highlights must not match, as they would trigger on nearly every message.
+const COMMON_TEXT_SAMPLE: &str =
+    "Hey, can someone help me? I tried it again and now it works, thanks a lot for that! \
+    Why does this happen when I do that? No idea, maybe ask them. OK, good to know 123.";
+
 lazy_static::lazy_static! {
     static ref BLOCKED_WORDS: HashSet<unicase::Ascii<&'static str>> = include_str!("./highlights_forbidden_words").lines().map(unicase::Ascii::new).collect();
 }
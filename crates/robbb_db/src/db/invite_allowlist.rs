@@ -0,0 +1,65 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serenity::model::id::{GuildId, UserId};
+
+use super::Db;
+
+/// A server that may be advertised with invite links.
+#[derive(Debug, Clone)]
+pub struct InviteAllowlistEntry {
+    pub guild_id: GuildId,
+    pub guild_name: String,
+    pub added_by: UserId,
+    pub create_date: Option<DateTime<Utc>>,
+}
+
+impl Db {
+    #[tracing::instrument(skip_all)]
+    pub async fn get_invite_allowlist(&self) -> Result<Vec<InviteAllowlistEntry>> {
+        Ok(sqlx::query!("select * from invite_allowlist")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| InviteAllowlistEntry {
+                guild_id: GuildId::new(x.guild_id as u64),
+                guild_name: x.guild_name,
+                added_by: UserId::new(x.added_by as u64),
+                create_date: x
+                    .create_date
+                    .map(|date| DateTime::from_naive_utc_and_offset(date, Utc)),
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all, fields(invite_allowlist.guild_id = %guild_id))]
+    pub async fn add_invite_allowlist_entry(
+        &self,
+        added_by: UserId,
+        guild_id: GuildId,
+        guild_name: &str,
+    ) -> Result<()> {
+        let added_by: i64 = added_by.into();
+        let guild_id: i64 = guild_id.into();
+        let now = Utc::now();
+        sqlx::query!(
+            "insert or replace into invite_allowlist (guild_id, guild_name, added_by, create_date) values (?, ?, ?, ?)",
+            guild_id,
+            guild_name,
+            added_by,
+            now,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Remove a server from the invite allowlist. Returns false if it wasn't on the allowlist.
+    #[tracing::instrument(skip_all, fields(invite_allowlist.guild_id = %guild_id))]
+    pub async fn remove_invite_allowlist_entry(&self, guild_id: GuildId) -> Result<bool> {
+        let guild_id: i64 = guild_id.into();
+        let result = sqlx::query!("delete from invite_allowlist where guild_id=?", guild_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -9,21 +9,38 @@ use serenity::prelude::TypeMapKey;
 use sqlx::SqlitePool;
 use std::collections::HashMap;
 pub mod blocklist;
+pub mod domain_filter;
 pub mod emoji_logging;
 pub mod fetch;
 pub mod fetch_field;
+pub mod highlight_ignores;
+pub mod highlight_settings;
 pub mod highlights;
 pub mod htm;
+pub mod invite_allowlist;
 pub mod mod_action;
 pub mod mute;
+pub mod phishing_domain;
 pub mod tag;
+pub mod tag_proposal;
+pub mod tag_trigger;
+pub mod verification;
 
 #[derive(Debug)]
 pub struct Db {
     pool: SqlitePool,
     blocklist_cache: Arc<RwLock<Option<Vec<String>>>>,
+    blocklist_regex_cache: RwLock<Option<regex::Regex>>,
+    /// The blocklist patterns compiled individually, together with the patterns in the same order.
+    blocklist_regex_set_cache: RwLock<Option<(regex::RegexSet, Vec<String>)>>,
+    domain_filter_cache: RwLock<Option<domain_filter::DomainFilter>>,
     highlight_cache: RwLock<Option<highlights::HighlightsData>>,
-    tag_name_cache: RwLock<Option<HashSet<String>>>,
+    highlight_ignore_cache: RwLock<Option<highlight_ignores::HighlightIgnores>>,
+    highlight_settings_cache:
+        RwLock<Option<HashMap<UserId, highlight_settings::HighlightSettings>>>,
+    tag_list_cache: RwLock<Option<Vec<tag::TagSummary>>>,
+    tag_trigger_cache: RwLock<Option<Vec<tag_trigger::TagTrigger>>>,
+    phishing_domain_cache: RwLock<Option<Arc<HashSet<String>>>>,
 }
 
 impl TypeMapKey for Db {
@@ -36,8 +53,15 @@ impl Db {
         Ok(Self {
             pool,
             blocklist_cache: Arc::new(RwLock::new(None)),
+            blocklist_regex_cache: RwLock::new(None),
+            blocklist_regex_set_cache: RwLock::new(None),
+            domain_filter_cache: RwLock::new(None),
             highlight_cache: RwLock::new(None),
-            tag_name_cache: RwLock::new(None),
+            highlight_ignore_cache: RwLock::new(None),
+            highlight_settings_cache: RwLock::new(None),
+            tag_list_cache: RwLock::new(None),
+            tag_trigger_cache: RwLock::new(None),
+            phishing_domain_cache: RwLock::new(None),
         })
     }
 
@@ -0,0 +1,65 @@
+use std::{collections::HashSet, sync::Arc};
+
+use anyhow::Result;
+use chrono::Utc;
+use serenity::model::id::UserId;
+
+use super::Db;
+
+impl Db {
+    #[tracing::instrument(skip_all)]
+    pub async fn get_phishing_domains(&self) -> Result<Arc<HashSet<String>>> {
+        // This is checked for every message containing a link, so avoid the write lock when possible
+        if let Some(domains) = self.phishing_domain_cache.read().await.as_ref() {
+            return Ok(domains.clone());
+        }
+        let mut cache = self.phishing_domain_cache.write().await;
+        if let Some(domains) = cache.as_ref() {
+            return Ok(domains.clone());
+        }
+        let domains: Arc<HashSet<String>> = Arc::new(
+            sqlx::query_scalar!(r#"select domain as "domain!" from phishing_domain"#)
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .collect(),
+        );
+        *cache = Some(domains.clone());
+        Ok(domains)
+    }
+
+    /// Add domains to the phishing domain list, ignoring ones that are already on it.
+    /// Returns the number of domains that were newly added.
+    #[tracing::instrument(skip_all, fields(phishing_domain.cnt = domains.len()))]
+    pub async fn add_phishing_domains(&self, added_by: UserId, domains: &[String]) -> Result<u64> {
+        let added_by: i64 = added_by.into();
+        let now = Utc::now();
+        let mut trans = self.pool.begin().await?;
+        let mut added_cnt = 0;
+        for domain in domains {
+            added_cnt += sqlx::query!(
+                "insert or ignore into phishing_domain (domain, added_by, create_date) values (?, ?, ?)",
+                domain,
+                added_by,
+                now,
+            )
+            .execute(&mut *trans)
+            .await?
+            .rows_affected();
+        }
+        trans.commit().await?;
+
+        *self.phishing_domain_cache.write().await = None;
+        Ok(added_cnt)
+    }
+
+    /// Remove a domain from the phishing domain list. Returns false if it wasn't on the list.
+    #[tracing::instrument(skip_all, fields(phishing_domain.domain = %domain))]
+    pub async fn remove_phishing_domain(&self, domain: &str) -> Result<bool> {
+        let result = sqlx::query!("delete from phishing_domain where domain=?", domain)
+            .execute(&self.pool)
+            .await?;
+        *self.phishing_domain_cache.write().await = None;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -1,59 +1,172 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use serenity::model::id::UserId;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, UserId};
+use sqlx::SqliteConnection;
 
 use super::Db;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Tag {
     pub name: String,
     pub moderator: UserId,
+    /// Text of the tag. For rich embed tags, this is the description of the embed.
     pub content: String,
     pub official: bool,
     pub create_date: Option<DateTime<Utc>>,
+    pub category: Option<String>,
+    /// Structure of the embed, if this is a rich embed tag.
+    pub embed: Option<TagEmbed>,
+}
+
+/// Everything of a rich embed tag apart from its description.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagEmbed {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<TagEmbedField>,
+    /// Color of the embed, as `#rrggbb`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<String>,
+    /// Images shown in the embed. More than one are shown as a gallery.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagEmbedField {
+    pub name: String,
+    pub value: String,
+    #[serde(default)]
+    pub inline: bool,
+}
+
+fn parse_tag_embed(embed: Option<String>) -> Result<Option<TagEmbed>> {
+    embed.map(|x| serde_json::from_str(&x).context("Failed to deserialize tag embed")).transpose()
+}
+
+/// Name, category and aliases of a tag, as used for listing and autocompleting tags.
+#[derive(Debug, Clone)]
+pub struct TagSummary {
+    pub name: String,
+    pub category: Option<String>,
+    pub aliases: Vec<String>,
+}
+
+/// A past or current version of a tag's content.
+#[derive(Debug, Clone)]
+pub struct TagRevision {
+    /// Number of this revision, counting from 1 for the oldest revision of the tag
+    pub number: usize,
+    pub tag: String,
+    pub moderator: UserId,
+    pub content: String,
+    pub create_date: Option<DateTime<Utc>>,
+    pub embed: Option<TagEmbed>,
+}
+
+/// How often a tag has been used.
+#[derive(Debug, Clone)]
+pub struct TagUsageStats {
+    pub name: String,
+    pub total_uses: i64,
+    /// Uses since the date the stats were requested for
+    pub recent_uses: i64,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+/// A tag found by a full-text search, with the part of its content that matched.
+#[derive(Debug, Clone)]
+pub struct TagSearchResult {
+    pub name: String,
+    /// Excerpt of the content around the match, with the matched words surrounded by `**`
+    pub snippet: String,
+}
+
+/// Turn arbitrary user input into an FTS5 query that matches tags containing all given words,
+/// treating each word as a prefix. Returns None if there are no words to search for.
+fn to_fts_query(query: &str) -> Option<String> {
+    let words = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .map(|word| format!("\"{}\"*", word))
+        .collect_vec();
+    if words.is_empty() {
+        None
+    } else {
+        Some(words.join(" "))
+    }
+}
+
+impl TagSummary {
+    /// Check if the name or any of the aliases starts with the given text, ignoring case.
+    pub fn matches_prefix(&self, partial: &str) -> bool {
+        let partial = partial.to_lowercase();
+        std::iter::once(&self.name)
+            .chain(self.aliases.iter())
+            .any(|x| x.to_lowercase().starts_with(&partial))
+    }
 }
 
 impl Db {
-    #[tracing::instrument(skip_all)]
-    pub async fn set_tag(
-        &self,
-        moderator: UserId,
-        name: String,
-        content: String,
-        official: bool,
-        create_date: Option<DateTime<Utc>>,
-    ) -> Result<Tag> {
-        let moderator_id: i64 = moderator.into();
-        sqlx::query!(
-            "insert into tag (name, moderator, content, official, create_date) values (?, ?, ?, ?, ?)
-                on conflict(name) do update set moderator=?, content=?, official=?, create_date=?",
-            name,
-            moderator_id,
-            content,
-            official,
-            create_date,
-            moderator_id,
-            content,
-            official,
-            create_date,
-        )
-            .execute(&self.pool)
-            .await?;
+    /// Create or update a tag, recording the new version in the tag's revision history.
+    /// The revision is attributed to the editor, which isn't necessarily who the tag is credited to.
+    #[tracing::instrument(skip_all, fields(tag.name = %tag.name))]
+    pub async fn set_tag(&self, tag: &Tag, editor: UserId) -> Result<()> {
+        let mut trans = self.pool.begin().await?;
+        write_tag(&mut trans, tag, editor).await?;
+        trans.commit().await?;
 
-        // Insert into the cache if there are already things in the cache.
-        // If there aren't yet, then we don't care, as the cache will be filled with all values when it's read for the first time.
-        if let Some(tag_names) = self.tag_name_cache.write().await.as_mut() {
-            tag_names.insert(name.clone());
+        *self.tag_list_cache.write().await = None;
+        Ok(())
+    }
+
+    /// Apply an import of tags in a single transaction, so a failing import doesn't leave half of it applied.
+    /// Deletes the removed tags, then writes the given tags and replaces their aliases.
+    /// Revisions are attributed to the author of each tag.
+    #[tracing::instrument(skip_all, fields(tag.removed_cnt = removed.len(), tag.written_cnt = tags.len()))]
+    pub async fn import_tags(&self, removed: &[String], tags: &[(Tag, Vec<String>)]) -> Result<()> {
+        let mut trans = self.pool.begin().await?;
+        for name in removed {
+            delete_tag_rows(&mut trans, name).await?;
+        }
+        // Remove aliases first, so aliases that moved from one tag to another can be added again afterwards
+        for (tag, _) in tags {
+            sqlx::query!("delete from tag_alias where tag=? COLLATE NOCASE", tag.name)
+                .execute(&mut *trans)
+                .await?;
+        }
+        for (tag, _) in tags {
+            write_tag(&mut trans, tag, tag.moderator).await?;
+        }
+        for (tag, aliases) in tags {
+            for alias in aliases {
+                sqlx::query!("insert into tag_alias (alias, tag) values (?, ?)", alias, tag.name)
+                    .execute(&mut *trans)
+                    .await
+                    .with_context(|| format!("Failed to add alias {alias} to {}", tag.name))?;
+            }
         }
+        trans.commit().await?;
 
-        Ok(Tag { name, moderator, content, official, create_date })
+        *self.tag_list_cache.write().await = None;
+        *self.tag_trigger_cache.write().await = None;
+        Ok(())
     }
 
+    /// Get a tag by its name or one of its aliases.
     #[tracing::instrument(skip_all)]
     pub async fn get_tag(&self, name: &str) -> Result<Option<Tag>> {
-        Ok(sqlx::query!(
-            r#"select name as "name!", moderator, content, official, create_date from tag where name=? COLLATE NOCASE"#,
-            name
+        sqlx::query!(
+            r#"select name as "name!", moderator, content, official, create_date, category, embed from tag
+               where name=? COLLATE NOCASE
+                  or name=(select tag from tag_alias where alias=? COLLATE NOCASE)"#,
+            name,
+            name,
         )
         .fetch_optional(&self.pool)
         .await?
@@ -61,43 +174,219 @@ impl Db {
             let create_date = x
                 .create_date
                 .map(|date| chrono::DateTime::from_naive_utc_and_offset(date, chrono::Utc));
-            Tag {
-            name: x.name,
-            moderator: UserId::new(x.moderator as u64),
-            content: x.content,
-            official: x.official,
-            create_date,
-        }}))
+            Ok(Tag {
+                name: x.name,
+                moderator: UserId::new(x.moderator as u64),
+                content: x.content,
+                official: x.official,
+                create_date,
+                category: x.category,
+                embed: parse_tag_embed(x.embed)?,
+            })
+        })
+        .transpose()
     }
 
     #[tracing::instrument(skip_all)]
     pub async fn delete_tag(&self, name: String) -> Result<()> {
-        sqlx::query!(r#"delete from tag where name=? COLLATE NOCASE"#, name)
+        let mut trans = self.pool.begin().await?;
+        delete_tag_rows(&mut trans, &name).await?;
+        trans.commit().await?;
+
+        *self.tag_list_cache.write().await = None;
+        *self.tag_trigger_cache.write().await = None;
+        Ok(())
+    }
+
+    /// Add an alias for a tag. The alias must not be the name or alias of another tag.
+    #[tracing::instrument(skip_all, fields(tag.name = %tag, tag.alias = %alias))]
+    pub async fn add_tag_alias(&self, tag: &str, alias: &str) -> Result<()> {
+        sqlx::query!("insert into tag_alias (alias, tag) values (?, ?)", alias, tag)
             .execute(&self.pool)
             .await?;
+        *self.tag_list_cache.write().await = None;
+        Ok(())
+    }
 
-        if let Some(tag_names) = self.tag_name_cache.write().await.as_mut() {
-            tag_names.remove(&name);
-        }
+    /// Remove an alias. Returns false if there was no such alias.
+    #[tracing::instrument(skip_all, fields(tag.alias = %alias))]
+    pub async fn remove_tag_alias(&self, alias: &str) -> Result<bool> {
+        let result = sqlx::query!("delete from tag_alias where alias=? COLLATE NOCASE", alias)
+            .execute(&self.pool)
+            .await?;
+        *self.tag_list_cache.write().await = None;
+        Ok(result.rows_affected() > 0)
+    }
 
+    /// Get all revisions of a tag, oldest first.
+    /// Revisions are kept when a tag is deleted, so this also works for tags that no longer exist.
+    #[tracing::instrument(skip_all)]
+    pub async fn get_tag_revisions(&self, name: &str) -> Result<Vec<TagRevision>> {
+        sqlx::query!(
+            r#"select tag, moderator, content, create_date, embed from tag_revision where tag=? COLLATE NOCASE order by id asc"#,
+            name,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .enumerate()
+        .map(|(idx, x)| {
+            Ok(TagRevision {
+                number: idx + 1,
+                tag: x.tag,
+                moderator: UserId::new(x.moderator as u64),
+                content: x.content,
+                create_date: x
+                    .create_date
+                    .map(|date| DateTime::from_naive_utc_and_offset(date, Utc)),
+                embed: parse_tag_embed(x.embed)?,
+            })
+        })
+        .collect()
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn record_tag_usage(
+        &self,
+        name: &str,
+        user: UserId,
+        channel: ChannelId,
+        date: DateTime<Utc>,
+    ) -> Result<()> {
+        let user: i64 = user.into();
+        let channel: i64 = channel.into();
+        sqlx::query!(
+            "insert into tag_usage (tag, usr, channel, create_date) values (?, ?, ?, ?)",
+            name,
+            user,
+            channel,
+            date,
+        )
+        .execute(&self.pool)
+        .await?;
         Ok(())
     }
 
+    /// Get the usage stats of all existing tags, counting recent uses since the given date.
     #[tracing::instrument(skip_all)]
-    pub async fn list_tags(&self) -> Result<Vec<String>> {
-        let tag_name_cache = self.tag_name_cache.read().await;
-        if let Some(tag_names) = tag_name_cache.as_ref() {
-            Ok(tag_names.clone().into_iter().collect())
-        } else {
-            std::mem::drop(tag_name_cache);
-            let tag_names = sqlx::query_scalar(r#"select name as "name!" from tag"#)
-                .fetch_all(&self.pool)
-                .await?;
+    pub async fn get_tag_usage_stats(&self, since: DateTime<Utc>) -> Result<Vec<TagUsageStats>> {
+        Ok(sqlx::query!(
+            r#"select tag.name as "name!",
+                      count(tag_usage.id) as "total_uses!: i64",
+                      coalesce(sum(tag_usage.create_date >= ?), 0) as "recent_uses!: i64",
+                      max(tag_usage.create_date) as "last_used: chrono::NaiveDateTime"
+               from tag left join tag_usage on tag_usage.tag = tag.name COLLATE NOCASE
+               group by tag.name"#,
+            since,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|x| TagUsageStats {
+            name: x.name,
+            total_uses: x.total_uses,
+            recent_uses: x.recent_uses,
+            last_used: x.last_used.map(|date| DateTime::from_naive_utc_and_offset(date, Utc)),
+        })
+        .collect())
+    }
 
-            let mut tag_name_cache = self.tag_name_cache.write().await;
-            let _ = tag_name_cache.insert(tag_names.clone().into_iter().collect());
+    /// Search through the names and contents of all tags, best matches first.
+    #[tracing::instrument(skip_all, fields(tag.search = %query))]
+    pub async fn search_tags(&self, query: &str, limit: i64) -> Result<Vec<TagSearchResult>> {
+        let Some(fts_query) = to_fts_query(query) else { return Ok(Vec::new()) };
+        Ok(sqlx::query!(
+            r#"select tag.name as "name!", snippet(tag_fts, 1, '**', '**', '...', 16) as "snippet!: String"
+               from tag_fts join tag on tag.name = tag_fts.name
+               where tag_fts match ?
+               order by bm25(tag_fts, 10.0, 1.0)
+               limit ?"#,
+            fts_query,
+            limit,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|x| TagSearchResult { name: x.name, snippet: x.snippet })
+        .collect())
+    }
 
-            Ok(tag_names)
+    #[tracing::instrument(skip_all)]
+    pub async fn list_tags(&self) -> Result<Vec<TagSummary>> {
+        let mut cache = self.tag_list_cache.write().await;
+        if let Some(tags) = cache.as_ref() {
+            return Ok(tags.clone());
         }
+
+        let mut aliases = sqlx::query!(r#"select alias as "alias!", tag from tag_alias"#)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| (x.tag, x.alias))
+            .into_group_map();
+        let tags = sqlx::query!(r#"select name as "name!", category from tag"#)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| TagSummary {
+                aliases: aliases.remove(&x.name).unwrap_or_default(),
+                name: x.name,
+                category: x.category,
+            })
+            .collect_vec();
+
+        *cache = Some(tags.clone());
+        Ok(tags)
     }
 }
+
+/// Insert or update a tag and record it as a new revision.
+async fn write_tag(conn: &mut SqliteConnection, tag: &Tag, editor: UserId) -> Result<()> {
+    let moderator_id: i64 = tag.moderator.into();
+    let editor_id: i64 = editor.into();
+    let embed = tag.embed.as_ref().map(serde_json::to_string).transpose()?;
+    sqlx::query!(
+        "insert into tag (name, moderator, content, official, create_date, category, embed) values (?, ?, ?, ?, ?, ?, ?)
+            on conflict(name) do update set moderator=?, content=?, official=?, create_date=?, category=?, embed=?",
+        tag.name,
+        moderator_id,
+        tag.content,
+        tag.official,
+        tag.create_date,
+        tag.category,
+        embed,
+        moderator_id,
+        tag.content,
+        tag.official,
+        tag.create_date,
+        tag.category,
+        embed,
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        "insert into tag_revision (tag, moderator, content, create_date, embed) values (?, ?, ?, ?, ?)",
+        tag.name,
+        editor_id,
+        tag.content,
+        tag.create_date,
+        embed,
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// Delete a tag together with its aliases and triggers. Its revisions are kept.
+async fn delete_tag_rows(conn: &mut SqliteConnection, name: &str) -> Result<()> {
+    sqlx::query!(r#"delete from tag_alias where tag=? COLLATE NOCASE"#, name)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!(r#"delete from tag_trigger where tag=? COLLATE NOCASE"#, name)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!(r#"delete from tag where name=? COLLATE NOCASE"#, name)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
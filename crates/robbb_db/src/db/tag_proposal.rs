@@ -0,0 +1,152 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serenity::model::id::UserId;
+
+use super::Db;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagProposalStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl TagProposalStatus {
+    pub fn from_i64(n: i64) -> Result<Self> {
+        match n {
+            0 => Ok(TagProposalStatus::Pending),
+            1 => Ok(TagProposalStatus::Approved),
+            2 => Ok(TagProposalStatus::Rejected),
+            _ => anyhow::bail!("Invalid tag proposal status: {}", n),
+        }
+    }
+
+    pub fn as_i64(&self) -> i64 {
+        match self {
+            TagProposalStatus::Pending => 0,
+            TagProposalStatus::Approved => 1,
+            TagProposalStatus::Rejected => 2,
+        }
+    }
+}
+
+impl std::fmt::Display for TagProposalStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TagProposalStatus::Pending => write!(f, "Pending"),
+            TagProposalStatus::Approved => write!(f, "Approved"),
+            TagProposalStatus::Rejected => write!(f, "Rejected"),
+        }
+    }
+}
+
+/// A tag suggested by a regular member, waiting for a moderator to approve or reject it.
+#[derive(Debug, Clone)]
+pub struct TagProposal {
+    pub id: i64,
+    pub name: String,
+    pub content: String,
+    pub user: UserId,
+    pub create_date: DateTime<Utc>,
+    pub status: TagProposalStatus,
+    pub moderator: Option<UserId>,
+}
+
+impl Db {
+    #[tracing::instrument(skip_all, fields(tag_proposal.name = %name, tag_proposal.user = %user))]
+    pub async fn add_tag_proposal(
+        &self,
+        name: String,
+        content: String,
+        user: UserId,
+        create_date: DateTime<Utc>,
+    ) -> Result<TagProposal> {
+        let user_id: i64 = user.into();
+        let id = sqlx::query!(
+            "insert into tag_proposal (name, content, usr, create_date) values (?, ?, ?, ?)",
+            name,
+            content,
+            user_id,
+            create_date,
+        )
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(TagProposal {
+            id,
+            name,
+            content,
+            user,
+            create_date,
+            status: TagProposalStatus::Pending,
+            moderator: None,
+        })
+    }
+
+    #[tracing::instrument(skip_all, fields(tag_proposal.id = %id))]
+    pub async fn get_tag_proposal(&self, id: i64) -> Result<Option<TagProposal>> {
+        sqlx::query!(
+            "select id, name, content, usr, create_date, status, moderator from tag_proposal where id=?",
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|x| {
+            Ok(TagProposal {
+                id: x.id,
+                name: x.name,
+                content: x.content,
+                user: UserId::new(x.usr as u64),
+                create_date: DateTime::from_naive_utc_and_offset(x.create_date, Utc),
+                status: TagProposalStatus::from_i64(x.status)?,
+                moderator: x.moderator.map(|x| UserId::new(x as u64)),
+            })
+        })
+        .transpose()
+    }
+
+    #[tracing::instrument(skip_all)]
+    pub async fn count_pending_tag_proposals(&self, user: UserId) -> Result<i32> {
+        let user: i64 = user.into();
+        Ok(sqlx::query_scalar!("select count(*) from tag_proposal where usr=? and status=0", user)
+            .fetch_one(&self.pool)
+            .await?)
+    }
+
+    /// Change the name and content of a proposal that is still pending.
+    /// Returns false if the proposal doesn't exist or was already resolved.
+    #[tracing::instrument(skip_all, fields(tag_proposal.id = %id))]
+    pub async fn edit_tag_proposal(&self, id: i64, name: &str, content: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            "update tag_proposal set name=?, content=? where id=? and status=0",
+            name,
+            content,
+            id,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Approve or reject a pending proposal.
+    /// Returns false if the proposal doesn't exist or was already resolved.
+    #[tracing::instrument(skip_all, fields(tag_proposal.id = %id, tag_proposal.status = %status))]
+    pub async fn resolve_tag_proposal(
+        &self,
+        id: i64,
+        status: TagProposalStatus,
+        moderator: UserId,
+    ) -> Result<bool> {
+        let status = status.as_i64();
+        let moderator: i64 = moderator.into();
+        let result = sqlx::query!(
+            "update tag_proposal set status=?, moderator=? where id=? and status=0",
+            status,
+            moderator,
+            id,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
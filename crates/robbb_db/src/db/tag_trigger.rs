@@ -0,0 +1,100 @@
+use anyhow::{bail, Result};
+use serenity::model::id::UserId;
+
+use super::Db;
+
+/// Longest pattern that can be used as a tag trigger.
+const MAX_TAG_TRIGGER_LEN: usize = 200;
+const TAG_TRIGGER_REGEX_SIZE_LIMIT: usize = 64 * 1024;
+
+/// A pattern that makes the bot suggest a tag when a message in tech support matches it.
+#[derive(Debug, Clone)]
+pub struct TagTrigger {
+    pub tag: String,
+    pub pattern: String,
+    pub added_by: UserId,
+    regex: regex::Regex,
+}
+
+impl TagTrigger {
+    pub fn new(tag: String, pattern: String, added_by: UserId) -> Result<Self> {
+        let regex = compile_tag_trigger(&pattern)?;
+        Ok(Self { tag, pattern, added_by, regex })
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+}
+
+/// Compile a trigger pattern, matching case-insensitively.
+pub fn compile_tag_trigger(pattern: &str) -> Result<regex::Regex> {
+    if pattern.len() > MAX_TAG_TRIGGER_LEN {
+        bail!("Pattern is longer than {} characters", MAX_TAG_TRIGGER_LEN);
+    }
+    let regex = regex::RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .size_limit(TAG_TRIGGER_REGEX_SIZE_LIMIT)
+        .build()?;
+    if regex.is_match("") {
+        bail!("Pattern must not match empty text");
+    }
+    Ok(regex)
+}
+
+impl Db {
+    #[tracing::instrument(skip_all)]
+    pub async fn get_tag_triggers(&self) -> Result<Vec<TagTrigger>> {
+        let mut cache = self.tag_trigger_cache.write().await;
+        if let Some(triggers) = cache.as_ref() {
+            return Ok(triggers.clone());
+        }
+        let triggers = sqlx::query!("select tag, pattern, added_by from tag_trigger")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .filter_map(|x| {
+                let added_by = UserId::new(x.added_by as u64);
+                match TagTrigger::new(x.tag, x.pattern, added_by) {
+                    Ok(trigger) => Some(trigger),
+                    Err(err) => {
+                        tracing::warn!(error = %err, "Skipping invalid tag trigger");
+                        None
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        *cache = Some(triggers.clone());
+        Ok(triggers)
+    }
+
+    #[tracing::instrument(skip_all, fields(tag.name = %tag, tag.trigger = %pattern))]
+    pub async fn add_tag_trigger(&self, tag: &str, pattern: &str, added_by: UserId) -> Result<()> {
+        compile_tag_trigger(pattern)?;
+        let added_by: i64 = added_by.into();
+        sqlx::query!(
+            "insert into tag_trigger (tag, pattern, added_by) values (?, ?, ?)",
+            tag,
+            pattern,
+            added_by
+        )
+        .execute(&self.pool)
+        .await?;
+        *self.tag_trigger_cache.write().await = None;
+        Ok(())
+    }
+
+    /// Remove a trigger. Returns false if there was no such trigger.
+    #[tracing::instrument(skip_all, fields(tag.name = %tag, tag.trigger = %pattern))]
+    pub async fn remove_tag_trigger(&self, tag: &str, pattern: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            "delete from tag_trigger where tag=? COLLATE NOCASE and pattern=?",
+            tag,
+            pattern
+        )
+        .execute(&self.pool)
+        .await?;
+        *self.tag_trigger_cache.write().await = None;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,119 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serenity::model::id::UserId;
+
+use super::Db;
+
+#[derive(Debug)]
+pub struct Verification {
+    pub user: UserId,
+    pub join_date: DateTime<Utc>,
+    /// Members that haven't verified by this time get kicked.
+    pub deadline: DateTime<Utc>,
+    pub verified_date: Option<DateTime<Utc>>,
+    pub failed_attempts: i64,
+}
+
+impl Verification {
+    pub fn is_verified(&self) -> bool {
+        self.verified_date.is_some()
+    }
+}
+
+impl Db {
+    /// Start the verification of a member that just joined, resetting any previous verification state.
+    #[tracing::instrument(skip_all, fields(user.id = %user))]
+    pub async fn start_verification(
+        &self,
+        user: UserId,
+        join_date: DateTime<Utc>,
+        deadline: DateTime<Utc>,
+    ) -> Result<()> {
+        let user: i64 = user.into();
+        sqlx::query!(
+            "insert or replace into verification (usr, join_date, deadline, verified_date, failed_attempts) values (?, ?, ?, null, 0)",
+            user,
+            join_date,
+            deadline,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, fields(user.id = %user))]
+    pub async fn get_verification(&self, user: UserId) -> Result<Option<Verification>> {
+        let user: i64 = user.into();
+        Ok(sqlx::query!("select * from verification where usr=?", user)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|x| Verification {
+                user: UserId::new(x.usr as u64),
+                join_date: DateTime::from_naive_utc_and_offset(x.join_date, Utc),
+                deadline: DateTime::from_naive_utc_and_offset(x.deadline, Utc),
+                verified_date: x.verified_date.map(|x| DateTime::from_naive_utc_and_offset(x, Utc)),
+                failed_attempts: x.failed_attempts,
+            }))
+    }
+
+    /// Get all members that haven't verified before their deadline.
+    #[tracing::instrument(skip_all)]
+    pub async fn get_expired_verifications(&self) -> Result<Vec<Verification>> {
+        Ok(sqlx::query!(
+            "select * from verification
+             where verified_date is null
+               and cast(strftime('%s', deadline) as integer) < cast(strftime('%s', datetime('now')) as integer)"
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|x| Verification {
+            user: UserId::new(x.usr as u64),
+            join_date: DateTime::from_naive_utc_and_offset(x.join_date, Utc),
+            deadline: DateTime::from_naive_utc_and_offset(x.deadline, Utc),
+            verified_date: x.verified_date.map(|x| DateTime::from_naive_utc_and_offset(x, Utc)),
+            failed_attempts: x.failed_attempts,
+        })
+        .collect())
+    }
+
+    /// Record a failed verification attempt, returning the total number of failed attempts.
+    #[tracing::instrument(skip_all, fields(user.id = %user))]
+    pub async fn record_failed_verification(&self, user: UserId) -> Result<i64> {
+        let user: i64 = user.into();
+        Ok(sqlx::query_scalar!(
+            "update verification set failed_attempts = failed_attempts + 1 where usr=? returning failed_attempts",
+            user
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .unwrap_or_default())
+    }
+
+    #[tracing::instrument(skip_all, fields(user.id = %user))]
+    pub async fn set_verified(&self, user: UserId) -> Result<()> {
+        let user: i64 = user.into();
+        let now = Utc::now();
+        sqlx::query!("update verification set verified_date=? where usr=?", now, user)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Move the deadline of a verification, i.e. to retry kicking a member later.
+    #[tracing::instrument(skip_all, fields(user.id = %user))]
+    pub async fn postpone_verification(&self, user: UserId, deadline: DateTime<Utc>) -> Result<()> {
+        let user: i64 = user.into();
+        sqlx::query!("update verification set deadline=? where usr=?", deadline, user)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all, fields(user.id = %user))]
+    pub async fn remove_verification(&self, user: UserId) -> Result<()> {
+        let user: i64 = user.into();
+        sqlx::query!("delete from verification where usr=?", user).execute(&self.pool).await?;
+        Ok(())
+    }
+}
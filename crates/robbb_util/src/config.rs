@@ -37,6 +37,14 @@ pub struct Config {
     pub channel_mod_polls: ChannelId,
     pub channel_attachment_dump: Option<ChannelId>,
     pub channel_fake_cdn: ChannelId,
+    /// Channel nobody is supposed to post in. Non-staff posting there get banned.
+    pub channel_honeypot: Option<ChannelId>,
+
+    /// Members that joined less than this many hours ago may not post links.
+    pub link_filter_new_member_hours: Option<i64>,
+    pub spam_protection: SpamProtectionConfig,
+    /// Join verification is only enabled if an unverified role is configured.
+    pub verification: Option<VerificationConfig>,
 
     pub attachment_cache_path: PathBuf,
     pub attachment_cache_max_size: usize,
@@ -79,6 +87,11 @@ impl Config {
                 .map(ChannelId::new)
                 .ok(),
             channel_fake_cdn: ChannelId::new(parse_required_env_var("CHANNEL_FAKE_CDN")?),
+            channel_honeypot: parse_required_env_var("CHANNEL_HONEYPOT").map(ChannelId::new).ok(),
+            link_filter_new_member_hours: parse_required_env_var("LINK_FILTER_NEW_MEMBER_HOURS")
+                .ok(),
+            spam_protection: SpamProtectionConfig::from_environment(),
+            verification: VerificationConfig::from_environment()?,
             attachment_cache_path: parse_required_env_var("ATTACHMENT_CACHE_PATH")?,
             attachment_cache_max_size: parse_required_env_var("ATTACHMENT_CACHE_MAX_SIZE")?,
             time_started: chrono::Utc::now(),
@@ -102,6 +115,87 @@ impl Config {
     }
 }
 
+/// Thresholds for the automatic spam detection.
+/// A user exceeding any of these within the sliding window is considered to be spamming.
+#[derive(Debug, Clone)]
+pub struct SpamProtectionConfig {
+    pub window: std::time::Duration,
+    /// Messages in total
+    pub max_messages: usize,
+    /// Messages with identical content
+    pub max_duplicates: usize,
+    /// Distinct channels the same content was posted in
+    pub max_duplicate_channels: usize,
+    /// User, role and everyone mentions in total
+    pub max_mentions: usize,
+    /// Attachments in total
+    pub max_attachments: usize,
+    /// Emojis within a single message
+    pub max_emojis_per_message: usize,
+}
+
+impl SpamProtectionConfig {
+    /// Load the thresholds from the environment, falling back to defaults for the ones that aren't set.
+    pub fn from_environment() -> Self {
+        Self {
+            window: std::time::Duration::from_secs(
+                parse_required_env_var("SPAM_WINDOW_SECS").unwrap_or(30),
+            ),
+            max_messages: parse_required_env_var("SPAM_MAX_MESSAGES").unwrap_or(12),
+            max_duplicates: parse_required_env_var("SPAM_MAX_DUPLICATES").unwrap_or(4),
+            max_duplicate_channels: parse_required_env_var("SPAM_MAX_DUPLICATE_CHANNELS")
+                .unwrap_or(2),
+            max_mentions: parse_required_env_var("SPAM_MAX_MENTIONS").unwrap_or(15),
+            max_attachments: parse_required_env_var("SPAM_MAX_ATTACHMENTS").unwrap_or(12),
+            max_emojis_per_message: parse_required_env_var("SPAM_MAX_EMOJIS").unwrap_or(40),
+        }
+    }
+}
+
+/// Settings of the join verification, where new members have to press a button in the rules channel before they can talk.
+#[derive(Debug, Clone)]
+pub struct VerificationConfig {
+    /// Role given to members that haven't verified yet
+    pub role_unverified: RoleId,
+    /// Optional question members have to answer when verifying
+    pub question: Option<String>,
+    /// Expected answer to the question, compared case-insensitively
+    pub answer: Option<String>,
+    /// Accounts younger than this have to wait for `young_account_delay` after joining before they can verify
+    pub min_account_age: chrono::Duration,
+    pub young_account_delay: chrono::Duration,
+    /// Members that haven't verified within this time after joining get kicked
+    pub timeout: chrono::Duration,
+}
+
+impl VerificationConfig {
+    /// Load the verification settings, returning None if no unverified role is configured.
+    pub fn from_environment() -> anyhow::Result<Option<Self>> {
+        let Ok(role_unverified) = parse_required_env_var("ROLE_UNVERIFIED") else {
+            return Ok(None);
+        };
+        let question = required_env_var("VERIFICATION_QUESTION").ok();
+        let answer = required_env_var("VERIFICATION_ANSWER").ok();
+        if question.is_some() != answer.is_some() {
+            anyhow::bail!("VERIFICATION_QUESTION and VERIFICATION_ANSWER must be set together");
+        }
+        Ok(Some(Self {
+            role_unverified: RoleId::new(role_unverified),
+            question,
+            answer,
+            min_account_age: chrono::Duration::days(
+                parse_required_env_var("VERIFICATION_MIN_ACCOUNT_AGE_DAYS").unwrap_or(7),
+            ),
+            young_account_delay: chrono::Duration::minutes(
+                parse_required_env_var("VERIFICATION_YOUNG_ACCOUNT_DELAY_MINS").unwrap_or(10),
+            ),
+            timeout: chrono::Duration::hours(
+                parse_required_env_var("VERIFICATION_TIMEOUT_HOURS").unwrap_or(24),
+            ),
+        }))
+    }
+}
+
 impl TypeMapKey for Config {
     type Value = Arc<Config>;
 }
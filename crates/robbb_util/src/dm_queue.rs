@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serenity::{
+    builder::CreateMessage,
+    http::{Http, HttpError},
+    model::id::UserId,
+    prelude::TypeMapKey,
+};
+use tokio::sync::{mpsc, oneshot, Semaphore};
+
+/// Maximum number of DMs waiting to be sent. Further fire-and-forget DMs are dropped.
+const QUEUE_CAPACITY: usize = 1000;
+/// Maximum number of DMs being sent at the same time.
+const MAX_CONCURRENT_SENDS: usize = 4;
+/// How often sending a DM is attempted before giving up on transient errors.
+const MAX_ATTEMPTS: u32 = 3;
+/// How long users whose DMs were closed are skipped for.
+const CLOSED_DM_TTL: Duration = Duration::from_secs(60 * 60 * 6);
+/// How long [`DmQueue::deliver`] waits for a DM to be sent.
+const DELIVER_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Discord error code for "Cannot send messages to this user".
+const CANNOT_MESSAGE_USER_ERROR: isize = 50007;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DmError {
+    #[error("The user doesn't accept DMs")]
+    Closed,
+    #[error("The DM queue is full")]
+    QueueFull,
+    #[error("Timed out waiting for the DM to be sent")]
+    Timeout,
+    #[error("Failed to send DM: {0}")]
+    Failed(String),
+}
+
+/// Counters of what happened to the DMs sent through the queue since the bot started.
+#[derive(Debug, Default)]
+struct DmStats {
+    delivered: AtomicU64,
+    failed: AtomicU64,
+    closed: AtomicU64,
+    dropped: AtomicU64,
+    retries: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DmStatsSnapshot {
+    pub delivered: u64,
+    pub failed: u64,
+    /// DMs that weren't sent because the user doesn't accept DMs
+    pub closed: u64,
+    /// DMs that were dropped because the queue was full
+    pub dropped: u64,
+    pub retries: u64,
+    pub queued: usize,
+    pub known_closed_dms: usize,
+}
+
+struct DmJob {
+    user_id: UserId,
+    message: CreateMessage,
+    result: Option<oneshot::Sender<Result<(), DmError>>>,
+}
+
+/// Shared delivery service for all DMs the bot sends, with a bounded queue and limited concurrency,
+/// retrying transient errors and remembering users that don't accept DMs for a while.
+pub struct DmQueue {
+    sender: mpsc::Sender<DmJob>,
+    receiver: parking_lot::Mutex<Option<mpsc::Receiver<DmJob>>>,
+    closed_dms: parking_lot::Mutex<HashMap<UserId, Instant>>,
+    stats: DmStats,
+}
+
+impl std::fmt::Debug for DmQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DmQueue").field("stats", &self.stats).finish_non_exhaustive()
+    }
+}
+
+impl TypeMapKey for DmQueue {
+    type Value = Arc<DmQueue>;
+}
+
+impl Default for DmQueue {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        Self {
+            sender,
+            receiver: parking_lot::Mutex::new(Some(receiver)),
+            closed_dms: parking_lot::Mutex::new(HashMap::new()),
+            stats: DmStats::default(),
+        }
+    }
+}
+
+impl DmQueue {
+    /// Start sending out queued DMs. DMs queued before this is called are sent once it's started.
+    pub fn start(self: &Arc<Self>, http: Arc<Http>) {
+        let Some(mut receiver) = self.receiver.lock().take() else {
+            tracing::warn!("DM queue was already started");
+            return;
+        };
+        let queue = self.clone();
+        tokio::spawn(async move {
+            let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_SENDS));
+            while let Some(job) = receiver.recv().await {
+                let Ok(permit) = semaphore.clone().acquire_owned().await else { break };
+                let queue = queue.clone();
+                let http = http.clone();
+                tokio::spawn(async move {
+                    let result = queue.process(&http, job.user_id, job.message).await;
+                    if let Some(sender) = job.result {
+                        let _ = sender.send(result);
+                    }
+                    drop(permit);
+                });
+            }
+        });
+    }
+
+    /// Queue a DM without waiting for it to be sent.
+    /// If the queue is full, the DM is dropped.
+    pub fn send(&self, user_id: UserId, message: CreateMessage) {
+        let job = DmJob { user_id, message, result: None };
+        if self.sender.try_send(job).is_err() {
+            self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(user.id = %user_id, "DM queue is full, dropping DM");
+        }
+    }
+
+    /// Queue a DM and wait for it to be sent, i.e. before banning a user.
+    pub async fn deliver(&self, user_id: UserId, message: CreateMessage) -> Result<(), DmError> {
+        if self.is_closed(user_id) {
+            self.stats.closed.fetch_add(1, Ordering::Relaxed);
+            return Err(DmError::Closed);
+        }
+        let (result_sender, result_receiver) = oneshot::channel();
+        let job = DmJob { user_id, message, result: Some(result_sender) };
+        tokio::time::timeout(DELIVER_TIMEOUT, async {
+            self.sender.send(job).await.map_err(|_| DmError::QueueFull)?;
+            result_receiver.await.map_err(|_| DmError::Failed("DM queue stopped".to_string()))?
+        })
+        .await
+        .map_err(|_| DmError::Timeout)?
+    }
+
+    /// Forget that the user didn't accept DMs, i.e. because they explicitly asked to be DMed.
+    pub fn forget_closed_dms(&self, user_id: UserId) {
+        self.closed_dms.lock().remove(&user_id);
+    }
+
+    pub fn stats(&self) -> DmStatsSnapshot {
+        DmStatsSnapshot {
+            delivered: self.stats.delivered.load(Ordering::Relaxed),
+            failed: self.stats.failed.load(Ordering::Relaxed),
+            closed: self.stats.closed.load(Ordering::Relaxed),
+            dropped: self.stats.dropped.load(Ordering::Relaxed),
+            retries: self.stats.retries.load(Ordering::Relaxed),
+            queued: QUEUE_CAPACITY - self.sender.capacity(),
+            known_closed_dms: self.closed_dms.lock().len(),
+        }
+    }
+
+    fn is_closed(&self, user_id: UserId) -> bool {
+        self.closed_dms.lock().get(&user_id).is_some_and(|x| x.elapsed() < CLOSED_DM_TTL)
+    }
+
+    fn remember_closed(&self, user_id: UserId) {
+        let mut closed_dms = self.closed_dms.lock();
+        closed_dms.retain(|_, x| x.elapsed() < CLOSED_DM_TTL);
+        closed_dms.insert(user_id, Instant::now());
+    }
+
+    #[tracing::instrument(skip_all, fields(user.id = %user_id))]
+    async fn process(
+        &self,
+        http: &Arc<Http>,
+        user_id: UserId,
+        message: CreateMessage,
+    ) -> Result<(), DmError> {
+        if self.is_closed(user_id) {
+            self.stats.closed.fetch_add(1, Ordering::Relaxed);
+            return Err(DmError::Closed);
+        }
+        let mut attempt = 1;
+        loop {
+            let result = match user_id.create_dm_channel(http).await {
+                Ok(channel) => channel.send_message(http, message.clone()).await.map(|_| ()),
+                Err(err) => Err(err),
+            };
+            let err = match result {
+                Ok(()) => {
+                    self.stats.delivered.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+                Err(err) => err,
+            };
+
+            match classify_error(&err) {
+                DmFailure::Closed => {
+                    tracing::debug!("User doesn't accept DMs");
+                    self.stats.closed.fetch_add(1, Ordering::Relaxed);
+                    self.remember_closed(user_id);
+                    return Err(DmError::Closed);
+                }
+                DmFailure::Transient if attempt < MAX_ATTEMPTS => {
+                    tracing::debug!(error.message = %err, attempt, "Transient error sending DM, retrying");
+                    self.stats.retries.fetch_add(1, Ordering::Relaxed);
+                    tokio::time::sleep(Duration::from_secs(2u64.pow(attempt))).await;
+                    attempt += 1;
+                }
+                _ => {
+                    tracing::warn!(error.message = %err, "Failed to send DM");
+                    self.stats.failed.fetch_add(1, Ordering::Relaxed);
+                    return Err(DmError::Failed(err.to_string()));
+                }
+            }
+        }
+    }
+}
+
+enum DmFailure {
+    Closed,
+    Transient,
+    Permanent,
+}
+
+fn classify_error(err: &serenity::Error) -> DmFailure {
+    match err {
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(response)) => {
+            if response.error.code == CANNOT_MESSAGE_USER_ERROR {
+                DmFailure::Closed
+            } else if response.status_code.as_u16() == 429 || response.status_code.is_server_error()
+            {
+                DmFailure::Transient
+            } else {
+                DmFailure::Permanent
+            }
+        }
+        serenity::Error::Http(HttpError::Request(_)) => DmFailure::Transient,
+        _ => DmFailure::Permanent,
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{config::Config, embeds, log_error, prelude::Ctx, UpEmotes};
+use crate::{config::Config, dm_queue::DmQueue, embeds, log_error, prelude::Ctx, UpEmotes};
 
 use anyhow::{Context, Result};
 use itertools::Itertools;
@@ -39,45 +39,52 @@ pub impl<'a> Ctx<'a> {
         self.data().up_emotes.read().clone()
     }
 
+    fn get_dm_queue(&self) -> Arc<DmQueue> {
+        self.data().dm_queue.clone()
+    }
+
     fn is_prefix(&self) -> bool {
         matches!(self, poise::Context::Prefix(_))
     }
 
     /// Reply with an ephemeral embed.
-    async fn reply_embed_ephemeral_builder(
-        &self,
+    async fn reply_embed_ephemeral_builder<'s>(
+        &'s self,
         build: impl FnOnce(CreateEmbed) -> CreateEmbed + Send + Sync,
-    ) -> StdResult<ReplyHandle<'_>, serenity::Error> {
+    ) -> StdResult<ReplyHandle<'s>, serenity::Error> {
         self.reply_embed_ephemeral(build(embeds::base_embed(self))).await
     }
 
     /// Reply with an embed.
-    async fn reply_embed_builder(
-        &self,
+    async fn reply_embed_builder<'s>(
+        &'s self,
         build: impl FnOnce(CreateEmbed) -> CreateEmbed + Send + Sync,
-    ) -> StdResult<ReplyHandle<'_>, serenity::Error> {
+    ) -> StdResult<ReplyHandle<'s>, serenity::Error> {
         self.reply_embed(build(embeds::base_embed(self))).await
     }
 
     /// Reply with an embed.
-    async fn reply_embed(&self, embed: CreateEmbed) -> StdResult<ReplyHandle<'_>, serenity::Error> {
+    async fn reply_embed<'s>(
+        &'s self,
+        embed: CreateEmbed,
+    ) -> StdResult<ReplyHandle<'s>, serenity::Error> {
         let reply = CreateReply::default().ephemeral(false).embed(embed).reply(true);
         self.send(reply).await
     }
 
     /// Reply with an ephemeral embed.
-    async fn reply_embed_ephemeral(
-        &self,
+    async fn reply_embed_ephemeral<'s>(
+        &'s self,
         embed: CreateEmbed,
-    ) -> StdResult<ReplyHandle<'_>, serenity::Error> {
+    ) -> StdResult<ReplyHandle<'s>, serenity::Error> {
         let reply = CreateReply::default().ephemeral(true).embed(embed).reply(true);
         self.send(reply).await
     }
 
-    async fn say_success(
-        &self,
+    async fn say_success<'s>(
+        &'s self,
         text: impl Display + Send + Sync + 'static,
-    ) -> StdResult<ReplyHandle<'_>, serenity::Error> {
+    ) -> StdResult<ReplyHandle<'s>, serenity::Error> {
         tracing::info!(
             msg.ephemeral = true,
             msg.content = %text,
@@ -90,10 +97,10 @@ pub impl<'a> Ctx<'a> {
         .await
     }
 
-    async fn say_error(
-        &self,
+    async fn say_error<'s>(
+        &'s self,
         text: impl Display + Send + Sync + 'static,
-    ) -> StdResult<ReplyHandle<'_>, serenity::Error> {
+    ) -> StdResult<ReplyHandle<'s>, serenity::Error> {
         tracing::info!(
             msg.ephemeral = true,
             msg.content = %text,
@@ -105,10 +112,10 @@ pub impl<'a> Ctx<'a> {
         )
         .await
     }
-    async fn say_success_mod_action(
-        &self,
+    async fn say_success_mod_action<'s>(
+        &'s self,
         text: impl Display + Send + Sync + 'static,
-    ) -> StdResult<ReplyHandle<'_>, serenity::Error> {
+    ) -> StdResult<ReplyHandle<'s>, serenity::Error> {
         tracing::info!(
             msg.ephemeral = false,
             msg.content = %text,
@@ -166,6 +173,9 @@ pub impl client::Context {
     async fn get_db(&self) -> Arc<Db> {
         self.data.read().await.get::<Db>().cloned().unwrap()
     }
+    async fn get_dm_queue(&self) -> Arc<DmQueue> {
+        self.data.read().await.get::<DmQueue>().cloned().unwrap()
+    }
 
     async fn log_bot_action(
         &self,
@@ -295,7 +305,9 @@ pub impl ChannelId {
     }
 
     fn name_cached(&self, cache: &Cache) -> Option<String> {
-        self.to_channel_cached(cache).map(|c| c.name().to_string())
+        cache.guilds().into_iter().find_map(|guild_id| {
+            cache.guild(guild_id)?.channels.get(self).map(|c| c.name().to_string())
+        })
     }
 
     fn name_cached_or_fallback(&self, cache: &Cache) -> String {
@@ -1,9 +1,12 @@
 pub mod cdn_hack;
 pub mod collect_interaction;
 pub mod config;
+pub mod dm_queue;
 pub mod embeds;
 pub mod extensions;
 pub mod prelude;
+pub mod tag_archive;
+pub mod tag_template;
 pub mod util;
 
 use std::{collections::HashMap, sync::Arc};
@@ -66,4 +69,5 @@ pub struct UserData {
     pub config: Arc<config::Config>,
     pub db: Arc<Db>,
     pub up_emotes: Arc<parking_lot::RwLock<Option<Arc<UpEmotes>>>>,
+    pub dm_queue: Arc<dm_queue::DmQueue>,
 }
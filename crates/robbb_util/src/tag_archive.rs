@@ -0,0 +1,399 @@
+//! Import and export of all tags as a zip archive of markdown files, one per tag,
+//! with the tag's metadata stored as front-matter:
+//!
+//! ```text
+//! ---
+//! name: nvidia
+//! author: 123456789012345678
+//! official: true
+//! created: 2024-01-01T12:00:00+00:00
+//! category: Drivers
+//! aliases: nv, nvidia-wayland
+//! embed: {"title":"Nvidia","images":["https://example.com/nvidia.png"]}
+//! ---
+//! Content of the tag
+//! ```
+//!
+//! `embed` is only present for rich embed tags, in which case the content is the description of the embed.
+//! Tags without `official` are imported as unofficial, so a tag can't become official without saying so.
+//! The content is stored exactly as it is in the database,
+//! so fake-CDN references stay fake-CDN references and keep resolving after importing them again.
+
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use poise::serenity_prelude::UserId;
+use robbb_db::{
+    tag::{Tag, TagEmbed},
+    Db,
+};
+
+const FRONT_MATTER_DELIMITER: &str = "---";
+
+/// Limits on what is read from an archive. Tags are limited to a few KiB, so these are very generous.
+const MAX_ARCHIVE_ENTRIES: usize = 10_000;
+const MAX_ENTRY_BYTES: u64 = 256 * 1024;
+const MAX_TOTAL_BYTES: u64 = 32 * 1024 * 1024;
+
+/// A tag as it is stored in an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagFile {
+    pub name: String,
+    pub author: UserId,
+    pub official: bool,
+    pub created: Option<DateTime<Utc>>,
+    pub category: Option<String>,
+    pub aliases: Vec<String>,
+    pub embed: Option<TagEmbed>,
+    pub content: String,
+}
+
+impl TagFile {
+    pub fn to_markdown(&self) -> String {
+        let mut front_matter = vec![
+            format!("name: {}", self.name),
+            format!("author: {}", self.author),
+            format!("official: {}", self.official),
+        ];
+        if let Some(created) = self.created {
+            front_matter.push(format!("created: {}", created.to_rfc3339()));
+        }
+        if let Some(category) = &self.category {
+            front_matter.push(format!("category: {}", category));
+        }
+        if !self.aliases.is_empty() {
+            front_matter.push(format!("aliases: {}", self.aliases.join(", ")));
+        }
+        if let Some(embed) = &self.embed {
+            // serializing these plain structs can't fail
+            front_matter
+                .push(format!("embed: {}", serde_json::to_string(embed).unwrap_or_default()));
+        }
+        format!(
+            "{FRONT_MATTER_DELIMITER}\n{}\n{FRONT_MATTER_DELIMITER}\n{}",
+            front_matter.join("\n"),
+            self.content
+        )
+    }
+
+    pub fn from_markdown(text: &str) -> Result<Self> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let rest = text
+            .strip_prefix(FRONT_MATTER_DELIMITER)
+            .and_then(|x| x.strip_prefix('\n').or_else(|| x.strip_prefix("\r\n")))
+            .context("Missing front-matter")?;
+        let (front_matter, content) = rest
+            .split_once(&format!("\n{FRONT_MATTER_DELIMITER}\n"))
+            .or_else(|| rest.split_once(&format!("\n{FRONT_MATTER_DELIMITER}\r\n")))
+            .context("Unterminated front-matter")?;
+
+        let fields: HashMap<&str, &str> = front_matter
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let (key, value) =
+                    line.split_once(':').context(format!("Invalid line `{line}`"))?;
+                Ok((key.trim(), value.trim()))
+            })
+            .collect::<Result<_>>()?;
+
+        let name = fields.get("name").filter(|x| !x.is_empty()).context("Missing name")?;
+        let author = fields
+            .get("author")
+            .context("Missing author")?
+            .parse::<UserId>()
+            .context("Invalid author")?;
+        let official = match fields.get("official").copied() {
+            Some("true") => true,
+            Some("false") | None => false,
+            Some(other) => anyhow::bail!("Invalid value for official: `{other}`"),
+        };
+        let created = fields
+            .get("created")
+            .map(|x| DateTime::parse_from_rfc3339(x).map(|x| x.with_timezone(&Utc)))
+            .transpose()
+            .context("Invalid created date")?;
+        let category = fields.get("category").filter(|x| !x.is_empty()).map(|x| x.to_string());
+        let aliases = fields
+            .get("aliases")
+            .map(|x| x.split(',').map(|x| x.trim().to_string()).filter(|x| !x.is_empty()).collect())
+            .unwrap_or_default();
+        let embed = fields
+            .get("embed")
+            .map(|x| serde_json::from_str(x))
+            .transpose()
+            .context("Invalid embed")?;
+
+        Ok(TagFile {
+            name: name.to_string(),
+            author,
+            official,
+            created,
+            category,
+            aliases,
+            embed,
+            content: content.to_string(),
+        })
+    }
+
+    /// Whether importing this file over the given existing tag would change anything.
+    fn differs_from(&self, other: &TagFile) -> bool {
+        self.content != other.content
+            || self.author != other.author
+            || self.official != other.official
+            || self.category != other.category
+            || self.embed != other.embed
+            || self.aliases.iter().sorted().ne(other.aliases.iter().sorted())
+    }
+}
+
+/// Name of the file a tag is stored in, with anything that could be a problem in a file name replaced.
+fn file_name_for(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect();
+    format!("{}.md", name.trim_start_matches('.'))
+}
+
+/// Pack the given tags into a zip archive.
+pub fn write_archive(tags: &[TagFile]) -> Result<Vec<u8>> {
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    let mut used_names = HashMap::<String, usize>::new();
+    for tag in tags {
+        // Different tag names can end up as the same file name, so number the duplicates
+        let file_name = file_name_for(&tag.name);
+        let count = used_names.entry(file_name.to_lowercase()).or_default();
+        *count += 1;
+        let file_name = if *count > 1 {
+            format!("{}-{}.md", file_name.trim_end_matches(".md"), count)
+        } else {
+            file_name
+        };
+        writer.start_file(file_name, options)?;
+        writer.write_all(tag.to_markdown().as_bytes())?;
+    }
+    Ok(writer.finish()?.into_inner())
+}
+
+/// Read all markdown files in a zip archive.
+/// Archives with too many entries or too much content are rejected, so they can't exhaust the bot's memory.
+pub fn read_archive(bytes: &[u8]) -> Result<Vec<TagFile>> {
+    let mut archive =
+        zip::ZipArchive::new(std::io::Cursor::new(bytes)).context("Invalid zip file")?;
+    if archive.len() > MAX_ARCHIVE_ENTRIES {
+        anyhow::bail!("The archive contains more than {MAX_ARCHIVE_ENTRIES} files");
+    }
+    let mut tags = Vec::new();
+    let mut total_bytes = 0;
+    for idx in 0..archive.len() {
+        let file = archive.by_index(idx)?;
+        if !file.is_file() || !file.name().ends_with(".md") {
+            continue;
+        }
+        let file_name = file.name().to_string();
+        if file.size() > MAX_ENTRY_BYTES {
+            anyhow::bail!("{file_name} is too large");
+        }
+        // The declared size can't be trusted, so also limit how much actually gets decompressed
+        let mut text = String::new();
+        file.take(MAX_ENTRY_BYTES + 1)
+            .read_to_string(&mut text)
+            .with_context(|| format!("Failed to read {file_name}"))?;
+        if text.len() as u64 > MAX_ENTRY_BYTES {
+            anyhow::bail!("{file_name} is too large");
+        }
+        total_bytes += text.len() as u64;
+        if total_bytes > MAX_TOTAL_BYTES {
+            anyhow::bail!("The archive's contents are too large");
+        }
+        tags.push(
+            TagFile::from_markdown(&text)
+                .with_context(|| format!("Invalid tag file {file_name}"))?,
+        );
+    }
+    if let Some(duplicate) = tags.iter().map(|x| x.name.to_lowercase()).duplicates().next() {
+        anyhow::bail!("The archive contains the tag {duplicate} more than once");
+    }
+    // The archive replaces all tags, so names and aliases only need to be unique within it
+    let names_and_aliases = tags.iter().flat_map(|x| std::iter::once(&x.name).chain(&x.aliases));
+    if let Some(duplicate) = names_and_aliases.map(|x| x.to_lowercase()).duplicates().next() {
+        anyhow::bail!("{duplicate} is used as the name or alias of more than one tag");
+    }
+    Ok(tags)
+}
+
+/// What importing an archive would change compared to the tags that currently exist.
+#[derive(Debug, Default)]
+pub struct TagArchiveDiff {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl TagArchiveDiff {
+    pub fn new(current: &[TagFile], incoming: &[TagFile]) -> Self {
+        let current_by_name: HashMap<_, _> =
+            current.iter().map(|x| (x.name.to_lowercase(), x)).collect();
+        let incoming_by_name: HashMap<_, _> =
+            incoming.iter().map(|x| (x.name.to_lowercase(), x)).collect();
+
+        let mut diff = TagArchiveDiff::default();
+        for tag in incoming {
+            match current_by_name.get(&tag.name.to_lowercase()) {
+                None => diff.added.push(tag.name.clone()),
+                Some(existing) if tag.differs_from(existing) => diff.changed.push(tag.name.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = current
+            .iter()
+            .filter(|x| !incoming_by_name.contains_key(&x.name.to_lowercase()))
+            .map(|x| x.name.clone())
+            .collect();
+        diff.added.sort();
+        diff.changed.sort();
+        diff.removed.sort();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl std::fmt::Display for TagArchiveDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (title, names) in
+            [("Added", &self.added), ("Changed", &self.changed), ("Removed", &self.removed)]
+        {
+            let names = if names.is_empty() { "-".to_string() } else { names.join(", ") };
+            writeln!(f, "{title}: {names}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Load all tags from the database in the form they are exported in.
+pub async fn load_tag_files(db: &Db) -> Result<Vec<TagFile>> {
+    let mut tags = Vec::new();
+    for summary in db.list_tags().await? {
+        let Some(tag) = db.get_tag(&summary.name).await? else { continue };
+        tags.push(TagFile {
+            name: tag.name,
+            author: tag.moderator,
+            official: tag.official,
+            created: tag.create_date,
+            category: tag.category,
+            aliases: summary.aliases.into_iter().sorted().collect(),
+            embed: tag.embed,
+            content: tag.content,
+        });
+    }
+    tags.sort_by_key(|x| x.name.to_lowercase());
+    Ok(tags)
+}
+
+/// Make the tags in the database match the given archive contents,
+/// only touching the tags that the diff lists as added, changed or removed.
+/// Everything is applied in a single transaction.
+pub async fn apply_import(db: &Db, incoming: &[TagFile], diff: &TagArchiveDiff) -> Result<()> {
+    let current_names: HashMap<String, String> =
+        db.list_tags().await?.into_iter().map(|x| (x.name.to_lowercase(), x.name)).collect();
+
+    let to_write = incoming
+        .iter()
+        .filter(|x| diff.added.contains(&x.name) || diff.changed.contains(&x.name))
+        .map(|x| {
+            let tag = Tag {
+                // Tag names are case-insensitive, so keep the casing of tags that already exist
+                name: current_names.get(&x.name.to_lowercase()).cloned().unwrap_or(x.name.clone()),
+                moderator: x.author,
+                content: x.content.clone(),
+                official: x.official,
+                create_date: x.created,
+                category: x.category.clone(),
+                embed: x.embed.clone(),
+            };
+            (tag, x.aliases.clone())
+        })
+        .collect_vec();
+    db.import_tags(&diff.removed, &to_write).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use robbb_db::tag::TagEmbedField;
+
+    fn example_tag() -> TagFile {
+        TagFile {
+            name: "nvidia".to_string(),
+            author: UserId::new(123456789012345678),
+            official: true,
+            created: DateTime::parse_from_rfc3339("2024-01-01T12:00:00+00:00")
+                .ok()
+                .map(|x| x.with_timezone(&Utc)),
+            category: Some("Drivers".to_string()),
+            aliases: vec!["nv".to_string(), "nvidia-wayland".to_string()],
+            embed: Some(TagEmbed {
+                title: Some("Nvidia".to_string()),
+                fields: vec![TagEmbedField {
+                    name: "Driver".to_string(),
+                    value: "proprietary: yes".to_string(),
+                    inline: true,
+                }],
+                images: vec!["https://example.com/nvidia.png".to_string()],
+                ..TagEmbed::default()
+            }),
+            content: "Some content\n---\nwith: a line that looks like front-matter\n".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_markdown_round_trip() {
+        let tag = example_tag();
+        assert_eq!(TagFile::from_markdown(&tag.to_markdown()).unwrap(), tag);
+
+        let minimal = TagFile {
+            official: false,
+            created: None,
+            category: None,
+            aliases: Vec::new(),
+            embed: None,
+            content: String::new(),
+            ..example_tag()
+        };
+        assert_eq!(TagFile::from_markdown(&minimal.to_markdown()).unwrap(), minimal);
+    }
+
+    #[test]
+    fn test_archive_round_trip() {
+        let tags = vec![example_tag(), TagFile { name: "nvidia?".to_string(), ..example_tag() }];
+        let tags_without_aliases =
+            tags.into_iter().map(|x| TagFile { aliases: Vec::new(), ..x }).collect_vec();
+        let archive = write_archive(&tags_without_aliases).unwrap();
+        assert_eq!(read_archive(&archive).unwrap(), tags_without_aliases);
+    }
+
+    #[test]
+    fn test_missing_official_is_unofficial() {
+        let tag = TagFile::from_markdown("---\nname: foo\nauthor: 1\n---\nbar").unwrap();
+        assert!(!tag.official);
+        assert_eq!(tag.content, "bar");
+    }
+
+    #[test]
+    fn test_invalid_front_matter() {
+        assert!(TagFile::from_markdown("name: foo\n---\nbar").is_err());
+        assert!(TagFile::from_markdown("---\nname: foo\nauthor: 1\nbar").is_err());
+        assert!(TagFile::from_markdown("---\nname: foo\n---\nbar").is_err());
+        assert!(TagFile::from_markdown("---\nname: foo\nauthor: 1\nofficial: yes\n---\n").is_err());
+    }
+}
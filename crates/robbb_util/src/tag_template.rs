@@ -0,0 +1,119 @@
+//! Placeholders that can be used in tag content, such as `{user}` or `{arg1}`.
+//!
+//! Anything in braces that isn't a known placeholder is left untouched,
+//! so that existing tags containing things like shell variables (`${HOME}`) keep working.
+
+use poise::serenity_prelude::{ChannelId, Mentionable, UserId};
+
+/// Highest supported `{argN}` placeholder.
+pub const MAX_ARGS: usize = 9;
+
+lazy_static::lazy_static! {
+    static ref PLACEHOLDER_PATTERN: regex::Regex = regex::Regex::new(r"\{([a-zA-Z0-9_]+)\}").unwrap();
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum TagTemplateError {
+    #[error("`{{{0}}}` is not a valid placeholder, did you mean `{{{1}}}`?")]
+    WrongCase(String, String),
+    #[error(
+        "`{{{0}}}` is not a valid placeholder, arguments go from `{{arg1}}` to `{{arg{MAX_ARGS}}}`"
+    )]
+    InvalidArg(String),
+    #[error("This tag needs {0} argument(s)")]
+    MissingArgs(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Placeholder {
+    User,
+    Author,
+    Channel,
+    /// Zero-based index into the arguments
+    Arg(usize),
+}
+
+impl Placeholder {
+    fn parse(name: &str) -> Result<Option<Placeholder>, TagTemplateError> {
+        match name {
+            "user" => return Ok(Some(Placeholder::User)),
+            "author" => return Ok(Some(Placeholder::Author)),
+            "channel" => return Ok(Some(Placeholder::Channel)),
+            _ => {}
+        }
+        let lower = name.to_lowercase();
+        if ["user", "author", "channel"].contains(&lower.as_str()) {
+            return Err(TagTemplateError::WrongCase(name.to_string(), lower));
+        }
+        if let Some(number) = lower.strip_prefix("arg") {
+            return match number.parse::<usize>() {
+                Ok(n) if name.starts_with("arg") && (1..=MAX_ARGS).contains(&n) => {
+                    Ok(Some(Placeholder::Arg(n - 1)))
+                }
+                Ok(n) if (1..=MAX_ARGS).contains(&n) => {
+                    Err(TagTemplateError::WrongCase(name.to_string(), lower))
+                }
+                _ if number.chars().all(|c| c.is_ascii_digit()) => {
+                    Err(TagTemplateError::InvalidArg(name.to_string()))
+                }
+                _ => Ok(None),
+            };
+        }
+        Ok(None)
+    }
+}
+
+/// The values placeholders get replaced with.
+#[derive(Debug, Clone)]
+pub struct TagTemplateContext {
+    /// The user the tag is addressed to, if any. `{user}` falls back to the author otherwise.
+    pub user: Option<UserId>,
+    pub author: UserId,
+    pub channel: ChannelId,
+    pub args: Vec<String>,
+}
+
+/// Check that all placeholders in the content are valid.
+pub fn validate(content: &str) -> Result<(), TagTemplateError> {
+    for capture in PLACEHOLDER_PATTERN.captures_iter(content) {
+        Placeholder::parse(&capture[1])?;
+    }
+    Ok(())
+}
+
+/// Number of arguments the content needs, i.e. the highest `{argN}` it uses.
+pub fn required_args(content: &str) -> usize {
+    PLACEHOLDER_PATTERN
+        .captures_iter(content)
+        .filter_map(|capture| match Placeholder::parse(&capture[1]) {
+            Ok(Some(Placeholder::Arg(idx))) => Some(idx + 1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Replace all placeholders in the content, leaving invalid ones as they are.
+/// Arguments are escaped so they can't mention anyone.
+pub fn render(content: &str, ctx: &TagTemplateContext) -> Result<String, TagTemplateError> {
+    let required = required_args(content);
+    if ctx.args.len() < required {
+        return Err(TagTemplateError::MissingArgs(required));
+    }
+
+    let rendered = PLACEHOLDER_PATTERN.replace_all(content, |capture: &regex::Captures| {
+        match Placeholder::parse(&capture[1]) {
+            Ok(Some(Placeholder::User)) => ctx.user.unwrap_or(ctx.author).mention().to_string(),
+            Ok(Some(Placeholder::Author)) => ctx.author.mention().to_string(),
+            Ok(Some(Placeholder::Channel)) => ctx.channel.mention().to_string(),
+            Ok(Some(Placeholder::Arg(idx))) => escape_mentions(&ctx.args[idx]),
+            _ => capture[0].to_string(),
+        }
+    });
+    Ok(rendered.into_owned())
+}
+
+/// Break up anything that discord would turn into a mention, such as `@everyone` or `<@&role>`.
+fn escape_mentions(text: &str) -> String {
+    text.replace('@', "@\u{200B}")
+}
@@ -1,4 +1,5 @@
 use anyhow::{anyhow, Context, Result};
+use itertools::Itertools;
 use poise::serenity_prelude::GuildId;
 use serenity::{
     client::{self},
@@ -167,6 +168,21 @@ pub fn find_emojis(value: impl AsRef<str>) -> Vec<EmojiIdentifier> {
         .collect()
 }
 
+/// Find all discord invite codes in a String.
+/// This includes lightly obfuscated invites like `discord . gg / code` or `discord(dot)gg/code`.
+#[tracing::instrument(skip_all)]
+pub fn find_invite_codes(value: impl AsRef<str>) -> Vec<String> {
+    lazy_static::lazy_static! {
+        static ref FIND_INVITE: regex::Regex = regex::Regex::new(
+            r"(?i)discord(?:app)?\s*(?:\.|\(dot\)|\[dot\]|\sdot\s)\s*(?:gg|com\s*/\s*invite)\s*/\s*([a-z0-9-]{2,32})"
+        ).unwrap();
+    }
+    let normalized = value
+        .as_ref()
+        .replace(['\u{200b}', '\u{200c}', '\u{200d}', '\u{200e}', '\u{200f}', '\\'], "");
+    FIND_INVITE.captures_iter(&normalized).map(|x| x[1].to_string()).unique().collect()
+}
+
 /// Validate that a string is a valid URL.
 pub fn validate_url(value: &str) -> bool {
     url::Url::parse(value)
@@ -190,7 +206,7 @@ pub fn parse_backticked_string(s: &str) -> Option<&str> {
 
 /// Determine if a file is an image based on the file extension
 pub fn is_image_file(s: &str) -> bool {
-    match s.split('.').last() {
+    match s.split('.').next_back() {
         Some(ext) => matches!(ext, "png" | "jpg" | "jpeg" | "gif" | "webp"),
         None => false,
     }
@@ -0,0 +1,8 @@
+ALTER TABLE blocked_regexes ADD COLUMN create_date datetime;
+
+CREATE TABLE IF NOT EXISTS blocklist_stats (
+    pattern text primary key,
+    hit_count integer not null default 0,
+    last_hit datetime,
+    last_offender integer
+);
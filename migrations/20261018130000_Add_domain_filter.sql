@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS domain_filter (
+    domain text primary key,
+    allowed boolean not null,
+    added_by integer not null,
+    create_date datetime
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS invite_allowlist (
+    guild_id integer primary key,
+    guild_name text not null,
+    added_by integer not null,
+    create_date datetime
+);
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS phishing_domain (
+    domain text primary key,
+    added_by integer not null,
+    create_date datetime
+);
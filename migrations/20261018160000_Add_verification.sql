@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS verification (
+    usr integer primary key,
+    join_date datetime not null,
+    deadline datetime not null,
+    verified_date datetime,
+    failed_attempts integer not null default 0
+);
@@ -0,0 +1,2 @@
+-- 0: whole word, 1: substring, 2: regex
+ALTER TABLE highlights ADD COLUMN mode integer not null default 0;
@@ -0,0 +1,13 @@
+-- Channels or categories a user doesn't want to be highlighted in
+CREATE TABLE IF NOT EXISTS highlight_ignored_channel (
+    usr integer not null,
+    channel integer not null,
+    PRIMARY KEY (usr, channel)
+);
+
+-- Authors whose messages never trigger a users highlights
+CREATE TABLE IF NOT EXISTS highlight_blocked_user (
+    usr integer not null,
+    blocked_usr integer not null,
+    PRIMARY KEY (usr, blocked_usr)
+);
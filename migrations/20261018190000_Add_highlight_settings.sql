@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS highlight_settings (
+    usr integer primary key,
+    cooldown_minutes integer not null,
+    suppress_when_active boolean not null,
+    quiet_hours_start integer,
+    quiet_hours_end integer,
+    utc_offset_hours integer not null,
+    digest_minutes integer
+);
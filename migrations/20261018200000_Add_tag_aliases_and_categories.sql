@@ -0,0 +1,7 @@
+ALTER TABLE tag ADD COLUMN category text;
+
+CREATE TABLE IF NOT EXISTS tag_alias (
+    alias text primary key COLLATE NOCASE,
+    tag text not null,
+    FOREIGN KEY(tag) REFERENCES tag(name) ON DELETE CASCADE
+);
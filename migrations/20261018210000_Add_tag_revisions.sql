@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS tag_revision (
+    id integer primary key asc,
+    tag text not null COLLATE NOCASE,
+    moderator integer not null,
+    content text not null,
+    create_date datetime
+);
+
+CREATE INDEX IF NOT EXISTS tag_revision_tag ON tag_revision(tag);
+
+-- Keep the current state of every existing tag as its first revision
+INSERT INTO tag_revision (tag, moderator, content, create_date)
+    SELECT name, moderator, content, create_date FROM tag;
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS tag_usage (
+    id integer primary key asc,
+    tag text not null COLLATE NOCASE,
+    usr integer not null,
+    channel integer not null,
+    create_date datetime not null
+);
+
+CREATE INDEX IF NOT EXISTS tag_usage_tag ON tag_usage(tag);
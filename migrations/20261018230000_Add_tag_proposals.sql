@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS tag_proposal (
+    id integer primary key asc,
+    name text not null,
+    content text not null,
+    usr integer not null,
+    create_date datetime not null,
+    -- 0 = pending, 1 = approved, 2 = rejected
+    status integer not null default 0,
+    moderator integer
+);
@@ -0,0 +1,18 @@
+-- The index keeps its own copy of the tag text and is keyed by tag name rather than the rowid of tag,
+-- which isn't stable since tag has a text primary key and VACUUM may renumber it.
+CREATE VIRTUAL TABLE IF NOT EXISTS tag_fts USING fts5(name, content);
+
+CREATE TRIGGER IF NOT EXISTS tag_fts_insert AFTER INSERT ON tag BEGIN
+    INSERT INTO tag_fts(name, content) VALUES (new.name, new.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS tag_fts_delete AFTER DELETE ON tag BEGIN
+    DELETE FROM tag_fts WHERE name = old.name;
+END;
+
+CREATE TRIGGER IF NOT EXISTS tag_fts_update AFTER UPDATE ON tag BEGIN
+    DELETE FROM tag_fts WHERE name = old.name;
+    INSERT INTO tag_fts(name, content) VALUES (new.name, new.content);
+END;
+
+INSERT INTO tag_fts(name, content) SELECT name, content FROM tag WHERE name NOT IN (SELECT name FROM tag_fts);
@@ -0,0 +1,4 @@
+-- JSON of the embed structure (title, fields, color, images, ...) of rich embed tags.
+-- The description of the embed is stored in the content column, like for plain tags.
+ALTER TABLE tag ADD COLUMN embed text;
+ALTER TABLE tag_revision ADD COLUMN embed text;
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS tag_trigger (
+    tag text not null COLLATE NOCASE,
+    pattern text not null,
+    added_by integer not null,
+    PRIMARY KEY (tag, pattern)
+);
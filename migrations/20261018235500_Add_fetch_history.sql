@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS fetch_history (
+    id integer primary key autoincrement,
+    usr integer not null,
+    info text not null,
+    create_date datetime
+);
+CREATE INDEX IF NOT EXISTS fetch_history_usr on fetch_history(usr, id);